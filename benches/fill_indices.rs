@@ -0,0 +1,58 @@
+//! Compares the throughput of `AliasIndexTable::fill_indices` with `BatchIndexTable::fill_indices`
+//! on tables of increasing size. Run with `cargo bench --bench fill_indices`, and again with
+//! `--features simd` to include the vectorized path.
+
+extern crate rand;
+extern crate vosealias;
+
+use std::convert::TryFrom;
+use std::hint::black_box;
+use std::time::Instant;
+
+use rand::{SeedableRng, XorShiftRng};
+use vosealias::{AliasIndexTable, BatchIndexTable};
+
+
+const PICKS: usize = 1 << 16;
+const ROUNDS: usize = 40;
+const TRIALS: usize = 15;
+
+
+/// The fastest of several trials of `ROUNDS` calls to `fill`, in nanoseconds per pick.
+fn time<G: FnMut(&mut [usize])>(mut fill: G) -> f64 {
+    let mut out = vec![0; PICKS];
+
+    (0..TRIALS)
+        .map(|_| {
+            let start = Instant::now();
+
+            for _ in 0..ROUNDS {
+                fill(&mut out);
+                black_box(&out);
+            }
+
+            start.elapsed().as_secs_f64()
+        })
+        .fold(f64::INFINITY, f64::min) * 1e9 / (PICKS * ROUNDS) as f64
+}
+
+
+fn main() {
+    let mut rng = XorShiftRng::from_seed([0x193a_6754, 0xa8a7_d469, 0x9783_0e05, 0x113b_a7bb]);
+
+    for &n in &[100usize, 10_000, 1_000_000, 10_000_000] {
+        let weights: Vec<f64> = (0..n).map(|i| ((i * 7919) % 113) as f64 + 0.5).collect();
+        let table = AliasIndexTable::from_weights(&weights).unwrap();
+        let batch = BatchIndexTable::try_from(&table).unwrap();
+
+        let index = time(|out| table.fill_indices(&mut rng, out));
+        let batched = time(|out| batch.fill_indices(&mut rng, out));
+
+        println!("{:>10} entries: AliasIndexTable {:6.2} ns/pick, BatchIndexTable {:6.2} ns/pick, \
+                  {:5.2}x",
+                 n,
+                 index,
+                 batched,
+                 index / batched);
+    }
+}
@@ -0,0 +1,112 @@
+use std::cmp::Ordering;
+
+use num_traits::Float;
+use rand::Rng;
+
+use {AliasTable, AliasTableError, AliasThreshold};
+
+
+/// A shuffle bag built on an alias table. Draws are made in epochs of `k * n` draws, where `n` is
+/// the number of elements with a nonzero probability: every such element is drawn at least once
+/// per epoch, and the remaining draws of the epoch are shared out in proportion to the elements'
+/// probabilities. Within an epoch, the draws come in a uniformly random order. This avoids the
+/// long droughts that independent picks allow, at the cost of the draws no longer being
+/// independent.
+#[derive(Clone)]
+pub struct WeightedBag<T, F> {
+    table: AliasTable<T, F>,
+    // How many times each element is drawn per epoch.
+    counts: Vec<usize>,
+    // The draws left in the current epoch, which are taken from the back.
+    bag: Vec<usize>,
+}
+
+
+impl<T, F> WeightedBag<T, F>
+    where F: AliasThreshold + Float
+{
+    /// Build a shuffle bag over the elements of a table, with epochs of `k` draws per element
+    /// with a nonzero probability. `k` must be at least one.
+    pub fn new(table: AliasTable<T, F>, k: usize) -> Result<Self, AliasTableError> {
+        if k == 0 {
+            return Err(AliasTableError::InvalidParameter { name: "k" });
+        }
+
+        let ps = table.indices.cached_probabilities().to_vec();
+        let live = ps.iter().filter(|&&p| p > F::zero()).count();
+        let extra = live.checked_mul(k).ok_or(AliasTableError::CastFailure)? - live;
+        let extra_f = F::from(extra).ok_or(AliasTableError::CastFailure)?;
+
+        // Share out the extra draws by largest remainder, on top of one draw for each element.
+        let mut counts = vec![0; ps.len()];
+        let mut remainders = Vec::with_capacity(live);
+        let mut shared = 0;
+
+        for (idx, &p) in ps.iter().enumerate().filter(|&(_, &p)| p > F::zero()) {
+            let share = extra_f * p;
+            let whole = share.floor().to_usize().unwrap_or(0);
+            counts[idx] = 1 + whole;
+            shared += whole;
+            remainders.push((idx, share - share.floor()));
+        }
+
+        remainders.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal));
+
+        for &(idx, _) in remainders.iter().take(extra.saturating_sub(shared)) {
+            counts[idx] += 1;
+        }
+
+        Ok(WeightedBag {
+            table,
+            counts,
+            bag: Vec::new(),
+        })
+    }
+
+    /// Draw the next element from the bag, starting a new epoch if the current one is over.
+    pub fn draw<R: Rng>(&mut self, rng: &mut R) -> &T {
+        if self.bag.is_empty() {
+            for (idx, &count) in self.counts.iter().enumerate() {
+                self.bag.extend((0..count).map(|_| idx));
+            }
+
+            rng.shuffle(&mut self.bag);
+        }
+
+        let idx = self.bag.pop().unwrap();
+        &self.table.objs[idx]
+    }
+}
+
+
+impl<T, F> WeightedBag<T, F> {
+    /// The number of draws in each epoch.
+    pub fn epoch_len(&self) -> usize {
+        self.counts.iter().sum()
+    }
+
+    /// The number of draws left in the current epoch, before the bag is refilled.
+    pub fn remaining(&self) -> usize {
+        self.bag.len()
+    }
+
+    /// How many times the element at `index` is drawn in each epoch.
+    pub fn count(&self, index: usize) -> usize {
+        self.counts[index]
+    }
+
+    /// Discard the rest of the current epoch, so that the next draw starts a new one.
+    pub fn reset(&mut self) {
+        self.bag.clear();
+    }
+
+    /// The table the bag draws from.
+    pub fn table(&self) -> &AliasTable<T, F> {
+        &self.table
+    }
+
+    /// Take back the table the bag draws from.
+    pub fn into_table(self) -> AliasTable<T, F> {
+        self.table
+    }
+}
@@ -0,0 +1,311 @@
+use std::convert::TryFrom;
+
+use num_traits::Float;
+use rand::Rng;
+
+use {AliasEntry, AliasIndexTable, AliasTableError};
+
+
+/// The number of independent random number generators `BatchIndexTable::fill_indices` runs side
+/// by side, one for each 64-bit lane of an AVX2 register.
+const LANES: usize = 4;
+
+/// Buffers shorter than this are filled by calling `BatchIndexTable::pick` for every slot, since
+/// seeding the lanes costs as much as `2 * LANES` picks.
+const MIN_LANES_LEN: usize = 64;
+
+
+/// A copy of an `AliasIndexTable` laid out for batch picking. Every threshold is converted to a
+/// 64-bit integer fraction and packed together with its value and alias into 16 bytes, so a
+/// large table takes up fewer cache lines, and each pick needs one random number and one
+/// integer comparison. Picks follow the same distribution as `AliasIndexTable::pick_fast`.
+///
+/// `fill_indices` generates its random numbers in lanes which can be computed in parallel. With
+/// the `simd` feature, on x86-64 processors which support AVX2, detected at runtime, four lanes
+/// are generated and resolved at a time in vector registers, looking up their buckets with
+/// gathers. Everywhere else the same lanes are computed by a scalar loop, which returns exactly
+/// the same indices.
+#[derive(Clone, Debug)]
+pub struct BatchIndexTable {
+    buckets: Vec<Bucket>,
+}
+
+
+// The vectorized path reads the threshold and the value and alias pair as two 64-bit words.
+#[derive(Clone, Copy, Debug)]
+#[repr(C)]
+struct Bucket {
+    threshold: u64,
+    value: u32,
+    alias: u32,
+}
+
+
+/// The states of `LANES` independent xorshift128+ generators (Vigna, "Further scramblings of
+/// Marsaglia's xorshift generators", 2017), stored lane by lane so that they can be loaded
+/// straight into vector registers.
+#[derive(Clone, Copy, Debug)]
+struct Lanes {
+    s0: [u64; LANES],
+    s1: [u64; LANES],
+}
+
+
+impl BatchIndexTable {
+    /// The number of buckets in the table.
+    pub fn len(&self) -> usize {
+        self.buckets.len()
+    }
+
+    /// Whether the table has no buckets. Tables are never empty, so this is always `false`.
+    pub fn is_empty(&self) -> bool {
+        self.buckets.is_empty()
+    }
+
+    /// Pick a random index from the distribution using a single call to `Rng::next_u64`.
+    pub fn pick<R: Rng>(&self, rng: &mut R) -> usize {
+        let (bucket, frac) = self.split(rng.next_u64());
+        self.resolve(bucket, frac)
+    }
+
+    /// Fill a buffer with random indices from the distribution. Buffers of at least 64 slots
+    /// take eight numbers from `rng` to seed four xorshift128+ generators, which then
+    /// produce the random numbers for every pick; shorter buffers call `pick` for every slot.
+    /// The result depends only on the state of `rng`, not on whether the vectorized path is used.
+    pub fn fill_indices<R: Rng>(&self, rng: &mut R, out: &mut [usize]) {
+        if out.len() < MIN_LANES_LEN {
+            for slot in out {
+                *slot = self.pick(rng);
+            }
+
+            return;
+        }
+
+        let mut lanes = Lanes::seed(rng);
+        let body = out.len() - out.len() % LANES;
+        let (body, tail) = out.split_at_mut(body);
+
+        #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+        let vectorized = is_x86_feature_detected!("avx2");
+        #[cfg(not(all(feature = "simd", target_arch = "x86_64")))]
+        let vectorized = false;
+
+        if vectorized {
+            // Safe because AVX2 support has just been checked, and `body` is a whole number of
+            // groups of lanes.
+            #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+            unsafe {
+                avx2::fill(&self.buckets, &mut lanes, body)
+            };
+        } else {
+            for chunk in body.chunks_exact_mut(LANES) {
+                self.resolve_lanes(&mut lanes, chunk);
+            }
+        }
+
+        self.resolve_lanes(&mut lanes, tail);
+    }
+
+    /// Resolve up to `LANES` picks from the next output of every lane.
+    fn resolve_lanes(&self, lanes: &mut Lanes, out: &mut [usize]) {
+        for (slot, &x) in out.iter_mut().zip(&lanes.next()) {
+            let (bucket, frac) = self.split(x);
+            *slot = self.resolve(bucket, frac);
+        }
+    }
+
+    /// Split a random number into a bucket, from the high bits of its product with the number of
+    /// buckets, and a fraction, from the low bits.
+    fn split(&self, x: u64) -> (usize, u64) {
+        let product = x as u128 * self.buckets.len() as u128;
+        ((product >> 64) as usize, product as u64)
+    }
+
+    fn resolve(&self, bucket: usize, frac: u64) -> usize {
+        let bucket = self.buckets[bucket];
+        let idx = if frac < bucket.threshold { bucket.value } else { bucket.alias };
+        idx as usize
+    }
+}
+
+
+impl Lanes {
+    /// Seed every lane from `rng`. A lane must not start with an all-zero state, which would
+    /// only ever produce zeroes, so such a lane is given a fixed nonzero state instead.
+    fn seed<R: Rng>(rng: &mut R) -> Self {
+        let mut lanes = Lanes {
+            s0: [0; LANES],
+            s1: [0; LANES],
+        };
+
+        for lane in 0..LANES {
+            lanes.s0[lane] = rng.next_u64();
+            lanes.s1[lane] = rng.next_u64();
+
+            if lanes.s0[lane] == 0 && lanes.s1[lane] == 0 {
+                lanes.s0[lane] = 0x9e37_79b9_7f4a_7c15;
+            }
+        }
+
+        lanes
+    }
+
+    /// Advance every lane, returning the next output of each.
+    fn next(&mut self) -> [u64; LANES] {
+        let mut xs = [0; LANES];
+
+        for (lane, x) in xs.iter_mut().enumerate() {
+            let mut a = self.s0[lane];
+            let b = self.s1[lane];
+
+            *x = a.wrapping_add(b);
+            a ^= a << 23;
+            self.s0[lane] = b;
+            self.s1[lane] = a ^ b ^ (a >> 18) ^ (b >> 5);
+        }
+
+        xs
+    }
+}
+
+
+impl<'a, F> TryFrom<&'a AliasIndexTable<F>> for BatchIndexTable
+    where F: Float
+{
+    type Error = AliasTableError;
+
+    /// Copy an index table into the batch layout. Indices are stored as `u32`s, so this returns
+    /// `AliasTableError::CastFailure` for tables with more than `u32::MAX` entries.
+    fn try_from(table: &'a AliasIndexTable<F>) -> Result<Self, Self::Error> {
+        if u32::try_from(table.table.len()).is_err() {
+            return Err(AliasTableError::CastFailure);
+        }
+
+        let one = 2.0f64.powi(64);
+        let buckets = table.table
+            .iter()
+            .map(|entry| {
+                match *entry {
+                    AliasEntry::Aliased { threshold, value, alias } => {
+                        let frac = (threshold / table.scale).to_f64().unwrap_or(0.0);
+
+                        // Conversion saturates, so a threshold of one always keeps the value.
+                        Bucket {
+                            threshold: (frac * one) as u64,
+                            value: value as u32,
+                            alias: alias as u32,
+                        }
+                    }
+                    AliasEntry::Unaliased(idx) => {
+                        Bucket {
+                            threshold: u64::MAX,
+                            value: idx as u32,
+                            alias: idx as u32,
+                        }
+                    }
+                }
+            })
+            .collect();
+
+        Ok(BatchIndexTable { buckets })
+    }
+}
+
+
+#[cfg(all(feature = "simd", target_arch = "x86_64"))]
+mod avx2 {
+    use std::arch::x86_64::*;
+
+    use super::{Bucket, Lanes};
+
+
+    /// Fill `out`, whose length must be a multiple of four, in the same way as the scalar loop of
+    /// `BatchIndexTable::fill_indices`, advancing `lanes` past the numbers used. The table must
+    /// have at most `u32::MAX` buckets.
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn fill(buckets: &[Bucket], lanes: &mut Lanes, out: &mut [usize]) {
+        debug_assert!(out.len() % 4 == 0);
+
+        let words = buckets.as_ptr() as *const i64;
+        let n = _mm256_set1_epi64x(buckets.len() as i64);
+        let sign = _mm256_set1_epi64x(i64::MIN);
+        let low = _mm256_set1_epi64x(0xffff_ffff);
+
+        let mut s0 = _mm256_loadu_si256(lanes.s0.as_ptr() as *const __m256i);
+        let mut s1 = _mm256_loadu_si256(lanes.s1.as_ptr() as *const __m256i);
+
+        for i in (0..out.len()).step_by(4) {
+            // One step of xorshift128+ in every lane, as in `Lanes::next`.
+            let x = _mm256_add_epi64(s0, s1);
+            let a = _mm256_xor_si256(s0, _mm256_slli_epi64(s0, 23));
+            s0 = s1;
+            s1 = _mm256_xor_si256(_mm256_xor_si256(a, s1),
+                                  _mm256_xor_si256(_mm256_srli_epi64(a, 18),
+                                                   _mm256_srli_epi64(s0, 5)));
+
+            // The 96-bit product of `x` and the number of buckets, from two 32-bit halves. Its
+            // top 32 bits are the bucket and its low 64 bits are the fraction.
+            let lo = _mm256_mul_epu32(x, n);
+            let hi = _mm256_mul_epu32(_mm256_srli_epi64(x, 32), n);
+            let bucket = _mm256_srli_epi64(_mm256_add_epi64(hi, _mm256_srli_epi64(lo, 32)), 32);
+            let frac = _mm256_add_epi64(_mm256_slli_epi64(hi, 32), lo);
+
+            // Every bucket is two words, so bucket `b` starts at word `2 * b`.
+            let offsets = _mm256_slli_epi64(bucket, 1);
+            let threshold = _mm256_i64gather_epi64(words, offsets, 8);
+            let pair = _mm256_i64gather_epi64(words.add(1), offsets, 8);
+
+            // An unsigned comparison, by flipping the sign bits of a signed one.
+            let keep = _mm256_cmpgt_epi64(_mm256_xor_si256(threshold, sign),
+                                          _mm256_xor_si256(frac, sign));
+            let idx = _mm256_blendv_epi8(_mm256_srli_epi64(pair, 32),
+                                         _mm256_and_si256(pair, low),
+                                         keep);
+
+            _mm256_storeu_si256(out.as_mut_ptr().add(i) as *mut __m256i, idx);
+        }
+
+        _mm256_storeu_si256(lanes.s0.as_mut_ptr() as *mut __m256i, s0);
+        _mm256_storeu_si256(lanes.s1.as_mut_ptr() as *mut __m256i, s1);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use rand::{SeedableRng, XorShiftRng};
+
+    use super::Lanes;
+    use {AliasIndexTable, BatchIndexTable};
+
+    #[test]
+    fn fill_indices_matches_the_lanes() {
+        let weights: Vec<f64> = (0..1001).map(|i| ((i * 7919) % 113) as f64).collect();
+        let table = BatchIndexTable::try_from(&AliasIndexTable::from_weights(&weights).unwrap())
+            .unwrap();
+
+        for &len in &[63, 64, 67, 1000] {
+            let mut out = vec![0; len];
+            table.fill_indices(&mut XorShiftRng::from_seed([1, 2, 3, 4]), &mut out);
+
+            let mut lanes = Lanes::seed(&mut XorShiftRng::from_seed([1, 2, 3, 4]));
+            let mut expected = vec![0; len];
+
+            if len < super::MIN_LANES_LEN {
+                let mut rng = XorShiftRng::from_seed([1, 2, 3, 4]);
+
+                for slot in &mut expected {
+                    *slot = table.pick(&mut rng);
+                }
+            } else {
+                for chunk in expected.chunks_mut(super::LANES) {
+                    table.resolve_lanes(&mut lanes, chunk);
+                }
+            }
+
+            assert_eq!(out, expected, "buffer of {}", len);
+        }
+    }
+}
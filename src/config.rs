@@ -0,0 +1,36 @@
+use serde::{Deserialize, Serialize};
+
+use {AliasTable, AliasTableError, AliasWeight};
+
+
+/// A weighted list of values, in a form which can be loaded from configuration files. It is
+/// (de)serialized as a sequence of `{ value, weight }` entries, such as the JSON
+/// `[{ "value": "sword", "weight": 1.0 }, { "value": "gem", "weight": 0.1 }]`. Only available
+/// with the `serde` feature.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct WeightedList<T, F = f64> {
+    /// The entries of the list.
+    pub entries: Vec<WeightedEntry<T, F>>,
+}
+
+
+/// A single entry of a `WeightedList`.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct WeightedEntry<T, F = f64> {
+    /// The value of the entry.
+    pub value: T,
+    /// The weight of the entry.
+    pub weight: F,
+}
+
+
+impl<T, F> AliasTable<T, F>
+    where F: AliasWeight
+{
+    /// Construct an alias table from a weighted list, such as one loaded from a configuration
+    /// file. Only available with the `serde` feature.
+    pub fn from_config(list: WeightedList<T, F>) -> Result<Self, AliasTableError> {
+        AliasTable::try_from_iter(list.entries.into_iter().map(|entry| (entry.value, entry.weight)))
+    }
+}
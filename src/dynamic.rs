@@ -0,0 +1,537 @@
+use std::collections::BTreeMap;
+use std::collections::btree_map::Entry;
+
+use num_traits::Float;
+use rand::Rng;
+use rand::distributions::{IndependentSample, Sample};
+
+use {AliasTableError, AliasWeight, check_weight};
+
+
+/// A weighted table for workloads which change weights often, in the style of Matias, Vitter and
+/// Ni ("Dynamic generation of discrete random variates", 1993). Entries are grouped into levels
+/// by weight, where every weight in a level is within a factor of two of the others, and levels
+/// are grouped the same way into classes by their total weight.
+///
+/// A pick scans the classes, heaviest first, to choose one in proportion to its total weight,
+/// and then uses rejection to choose a level of that class and an entry of that level, each of
+/// which is accepted at least half of the time. The scan takes at most `log2(L) + 3` expected
+/// steps, where `L` is the number of levels in use, because the classes' totals fall off
+/// geometrically; `L` is bounded by the exponent range of `F`, so the scan takes at most 15
+/// expected steps for `f64` and 12 for `f32`, however many entries there are and however widely
+/// their weights are spread. Changing a weight only touches the levels and classes it moves
+/// between, which takes time logarithmic in `L`, rather than rebuilding the whole table.
+#[derive(Clone, Debug)]
+pub struct DynamicAliasTable<T, F> {
+    objs: Vec<T>,
+    weights: Vec<F>,
+    // For every entry with a nonzero weight, its level and its position in the level.
+    slots: Vec<Option<(i32, usize)>>,
+    levels: BTreeMap<i32, Level<F>>,
+    classes: BTreeMap<i32, Class<F>>,
+    total: F,
+    // Weight changes since the totals were last recomputed from scratch.
+    updates: usize,
+}
+
+
+/// An iterator which picks random elements from a `DynamicAliasTable`.
+#[derive(Clone)]
+pub struct DynamicAliasIterator<'a, T: 'a, F: 'a, R>
+    where R: Rng
+{
+    rng: R,
+    table: &'a DynamicAliasTable<T, F>,
+}
+
+
+/// The entries whose weights are in `[lower, 2 * lower)`.
+#[derive(Clone, Debug)]
+struct Level<F> {
+    members: Vec<usize>,
+    total: F,
+    lower: F,
+    // The level's class and its position in the class.
+    class: i32,
+    position: usize,
+}
+
+
+/// The levels whose total weights are in `[lower, 2 * lower)`, with a copy of each total.
+#[derive(Clone, Debug)]
+struct Class<F> {
+    levels: Vec<(i32, F)>,
+    total: F,
+    lower: F,
+}
+
+
+impl<T, F> DynamicAliasTable<T, F>
+    where F: AliasWeight + Float
+{
+    /// Construct a table which picks `objs[i]` in proportion to `weights[i]`. Returns an error
+    /// if the weights aren't a valid distribution, in the same way as `AliasTable::new`.
+    pub fn new(objs: Vec<T>, weights: &[F]) -> Result<Self, AliasTableError> {
+        if objs.len() != weights.len() {
+            return Err(AliasTableError::LengthMismatch {
+                values: objs.len(),
+                weights: weights.len(),
+            });
+        } else if objs.is_empty() {
+            return Err(AliasTableError::Empty);
+        }
+
+        let mut total = F::zero();
+
+        for (index, &w) in weights.iter().enumerate() {
+            check_weight(index, w)?;
+            total = total + w;
+        }
+
+        if total == F::zero() {
+            return Err(AliasTableError::ZeroTotalWeight);
+        } else if !total.is_finite() {
+            return Err(AliasTableError::WeightOverflow);
+        }
+
+        let mut table = DynamicAliasTable {
+            objs,
+            weights: weights.to_vec(),
+            slots: vec![None; weights.len()],
+            levels: BTreeMap::new(),
+            classes: BTreeMap::new(),
+            total,
+            updates: 0,
+        };
+
+        for index in 0..weights.len() {
+            table.insert(index);
+        }
+
+        table.recompute_totals();
+        Ok(table)
+    }
+
+    /// Pick a random index from the distribution, in `O(1)` expected time: the number of steps
+    /// is bounded by the exponent range of `F`, not by the number of entries.
+    pub fn pick_index<R: Rng>(&self, rng: &mut R) -> usize {
+        let class = self.pick_class(rng);
+        let two = F::one() + F::one();
+
+        // A class with a single level would only ever pick it again after a rejection.
+        let level = match class.levels[..] {
+            [(key, _)] => &self.levels[&key],
+            _ => {
+                loop {
+                    let (key, total) = class.levels[rng.gen_range(0, class.levels.len())];
+
+                    if F::sample_below(two, rng) < total / class.lower {
+                        break &self.levels[&key];
+                    }
+                }
+            }
+        };
+
+        loop {
+            let idx = level.members[rng.gen_range(0, level.members.len())];
+
+            // Dividing by a power of two is exact, even for subnormal weights.
+            if F::sample_below(two, rng) < self.weights[idx] / level.lower {
+                return idx;
+            }
+        }
+    }
+
+    /// Pick a random element from the distribution.
+    pub fn pick<R: Rng>(&self, rng: &mut R) -> &T {
+        &self.objs[self.pick_index(rng)]
+    }
+
+    /// Pick a random element from the distribution, returning both its index in the table and a
+    /// reference to it.
+    pub fn pick_entry<R: Rng>(&self, rng: &mut R) -> (usize, &T) {
+        let idx = self.pick_index(rng);
+        (idx, &self.objs[idx])
+    }
+
+    /// Pick a random element from the distribution, returning a clone of it rather than a
+    /// reference.
+    pub fn sample<R: Rng>(&self, rng: &mut R) -> T
+        where T: Clone
+    {
+        self.pick(rng).clone()
+    }
+
+    /// Fill a buffer with the indices of random elements from the distribution, like calling
+    /// `pick_index` once for every slot.
+    pub fn fill_indices<R: Rng>(&self, rng: &mut R, out: &mut [usize]) {
+        for slot in out {
+            *slot = self.pick_index(rng);
+        }
+    }
+
+    /// Given an RNG, produce an iterator that picks random elements from the distribution by
+    /// calling `pick` repeatedly with the given RNG.
+    pub fn iter<R: Rng>(&self, rng: R) -> DynamicAliasIterator<'_, T, F, R> {
+        DynamicAliasIterator {
+            rng,
+            table: self,
+        }
+    }
+
+    /// Change the weight of the element at `index`. Only the levels the element moves out of
+    /// and into, and their classes, are updated, which takes time logarithmic in the number of
+    /// levels. If the index or weight is invalid, or every weight would be zero, an error is
+    /// returned and the table is left unchanged.
+    pub fn set_weight(&mut self, index: usize, weight: F) -> Result<(), AliasTableError> {
+        if index >= self.weights.len() {
+            return Err(AliasTableError::InvalidParameter { name: "index" });
+        }
+
+        check_weight(index, weight)?;
+
+        let total = self.total - self.weights[index] + weight;
+
+        if !total.is_finite() {
+            return Err(AliasTableError::WeightOverflow);
+        }
+
+        if weight == F::zero() && self.is_only_entry(index) {
+            return Err(AliasTableError::ZeroTotalWeight);
+        }
+
+        self.take(index);
+        self.weights[index] = weight;
+        self.insert(index);
+        self.total = total;
+
+        // Adding and subtracting weights accumulates rounding error in the totals, so they are
+        // recomputed after as many updates as there are entries, which is constant amortized time.
+        self.updates += 1;
+        if self.updates >= self.weights.len() {
+            self.recompute_totals();
+        }
+
+        Ok(())
+    }
+
+    /// Add a value to the end of the table with the given weight. If the weight is invalid, an
+    /// error is returned and the table is left unchanged.
+    pub fn push(&mut self, value: T, weight: F) -> Result<(), AliasTableError> {
+        let index = self.weights.len();
+        check_weight(index, weight)?;
+
+        let total = self.total + weight;
+
+        if !total.is_finite() {
+            return Err(AliasTableError::WeightOverflow);
+        }
+
+        self.objs.push(value);
+        self.weights.push(weight);
+        self.slots.push(None);
+        self.insert(index);
+        self.total = total;
+        Ok(())
+    }
+
+    /// Remove the value at `index` from the table and return it. Later values move down by one
+    /// index, which takes time linear in the size of the table. If the index is out of range or
+    /// the value is the only one the table can pick, an error is returned and the table is left
+    /// unchanged.
+    pub fn remove(&mut self, index: usize) -> Result<T, AliasTableError> {
+        if index >= self.weights.len() {
+            return Err(AliasTableError::InvalidParameter { name: "index" });
+        } else if self.is_only_entry(index) {
+            return Err(AliasTableError::ZeroTotalWeight);
+        }
+
+        self.take(index);
+        self.weights.remove(index);
+        self.slots.remove(index);
+
+        for level in self.levels.values_mut() {
+            for member in &mut level.members {
+                if *member > index {
+                    *member -= 1;
+                }
+            }
+        }
+
+        self.recompute_totals();
+        Ok(self.objs.remove(index))
+    }
+
+    /// The weight of the element at `index`, or `None` if it is out of range.
+    pub fn weight(&self, index: usize) -> Option<F> {
+        self.weights.get(index).cloned()
+    }
+
+    /// The weights of the table, exactly as they were given, in the same order as `values`.
+    pub fn weights(&self) -> &[F] {
+        &self.weights
+    }
+
+    /// The sum of the weights of the table.
+    pub fn total_weight(&self) -> F {
+        self.total
+    }
+
+    /// The probability with which the element at `index` is picked, or `None` if it is out of
+    /// range.
+    pub fn probability_of(&self, index: usize) -> Option<F> {
+        self.weight(index).map(|w| w / self.total)
+    }
+
+    /// The values of the table, in the order they are indexed by `pick_index`.
+    pub fn values(&self) -> &[T] {
+        &self.objs
+    }
+
+    /// The number of elements in the table.
+    pub fn len(&self) -> usize {
+        self.objs.len()
+    }
+
+    /// Whether the table has no elements. Tables are never empty, so this is always `false`.
+    pub fn is_empty(&self) -> bool {
+        self.objs.is_empty()
+    }
+
+    /// Whether the entry at `index` is the only one with a nonzero weight.
+    fn is_only_entry(&self, index: usize) -> bool {
+        self.levels.len() == 1 && self.levels.values().all(|level| level.members == [index])
+    }
+
+    /// Choose a class in proportion to its total weight, heaviest first. A class's total is less
+    /// than twice its lower bound times its number of levels, so the classes more than
+    /// `log2(L) + 1` steps below the heaviest hold at most half of the remaining weight, and the
+    /// scan usually stops early.
+    fn pick_class<R: Rng>(&self, rng: &mut R) -> &Class<F> {
+        if self.classes.len() == 1 {
+            return self.classes.values().next().expect("the table has one class");
+        }
+
+        let mut u = F::sample_below(self.total, rng);
+        let mut chosen = None;
+
+        for class in self.classes.values().rev() {
+            chosen = Some(class);
+
+            if u < class.total {
+                break;
+            }
+
+            u = u - class.total;
+        }
+
+        // Tables always have at least one entry with a nonzero weight, so there is a class. If
+        // rounding error leaves `u` above every total, the lightest class is used.
+        chosen.expect("a DynamicAliasTable always has a class")
+    }
+
+    /// Add the entry at `index` to the level for its weight.
+    fn insert(&mut self, index: usize) {
+        let w = self.weights[index];
+
+        if w == F::zero() {
+            return;
+        }
+
+        let key = level_of(w);
+        let (old_total, new_level) = match self.levels.entry(key) {
+            Entry::Occupied(entry) => (entry.get().total, false),
+            Entry::Vacant(entry) => {
+                entry.insert(Level {
+                    members: Vec::new(),
+                    total: F::zero(),
+                    lower: pow2(key),
+                    class: 0,
+                    position: 0,
+                });
+                (F::zero(), true)
+            }
+        };
+
+        {
+            let level = self.levels.get_mut(&key).expect("the level was just found or added");
+            self.slots[index] = Some((key, level.members.len()));
+            level.members.push(index);
+            level.total = level.total + w;
+        }
+
+        if new_level {
+            self.join_class(key);
+        } else {
+            self.reclassify(key, old_total);
+        }
+    }
+
+    /// Remove the entry at `index` from its level, if it has one.
+    fn take(&mut self, index: usize) {
+        let (key, pos) = match self.slots[index].take() {
+            Some(slot) => slot,
+            None => return,
+        };
+
+        let (old_total, now_empty) = {
+            let weights = &self.weights;
+            let level = self.levels.get_mut(&key).expect("every slot refers to a level");
+            let old_total = level.total;
+            level.members.swap_remove(pos);
+            level.total = level.total - weights[index];
+
+            if let Some(&moved) = level.members.get(pos) {
+                self.slots[moved] = Some((key, pos));
+            }
+
+            // Every member weighs at least `lower`, so a smaller total is rounding error, which
+            // would put the level in the wrong class.
+            if level.total < level.lower {
+                level.total = level.members.iter().fold(F::zero(), |sum, &idx| sum + weights[idx]);
+            }
+
+            (old_total, level.members.is_empty())
+        };
+
+        if now_empty {
+            self.leave_class(key, old_total);
+            self.levels.remove(&key);
+        } else {
+            self.reclassify(key, old_total);
+        }
+    }
+
+    /// Update the class of the level `key` after its total has changed from `old_total`. Most
+    /// changes leave the level in the same class, and only adjust the class's total.
+    fn reclassify(&mut self, key: i32, old_total: F) {
+        let (class_key, position, total) = {
+            let level = &self.levels[&key];
+            (level.class, level.position, level.total)
+        };
+
+        if level_of(total) == class_key {
+            let class = self.classes.get_mut(&class_key).expect("every level has a class");
+            class.levels[position].1 = total;
+            class.total = class.total - old_total + total;
+        } else {
+            self.leave_class(key, old_total);
+            self.join_class(key);
+        }
+    }
+
+    /// Add the level `key` to the class for its total weight.
+    fn join_class(&mut self, key: i32) {
+        let level = self.levels.get_mut(&key).expect("only existing levels join a class");
+        let class_key = level_of(level.total);
+        let class = self.classes.entry(class_key).or_insert_with(|| {
+            Class {
+                levels: Vec::new(),
+                total: F::zero(),
+                lower: pow2(class_key),
+            }
+        });
+
+        level.class = class_key;
+        level.position = class.levels.len();
+        class.levels.push((key, level.total));
+        class.total = class.total + level.total;
+    }
+
+    /// Remove the level `key`, whose total was last `total`, from its class.
+    fn leave_class(&mut self, key: i32, total: F) {
+        let (class_key, position) = {
+            let level = &self.levels[&key];
+            (level.class, level.position)
+        };
+
+        let now_empty = {
+            let class = self.classes.get_mut(&class_key).expect("every level has a class");
+            class.levels.swap_remove(position);
+            class.total = class.total - total;
+
+            if let Some(&(moved, _)) = class.levels.get(position) {
+                self.levels.get_mut(&moved).expect("every class member is a level").position =
+                    position;
+            }
+
+            class.levels.is_empty()
+        };
+
+        if now_empty {
+            self.classes.remove(&class_key);
+        }
+    }
+
+    /// Recompute the total weight of every level, and of the table, from the weights, and
+    /// regroup the levels into classes by their new totals.
+    fn recompute_totals(&mut self) {
+        let weights = &self.weights;
+        self.total = F::zero();
+
+        for level in self.levels.values_mut() {
+            level.total = level.members.iter().fold(F::zero(), |sum, &idx| sum + weights[idx]);
+            self.total = self.total + level.total;
+        }
+
+        self.classes.clear();
+        let keys: Vec<_> = self.levels.keys().cloned().collect();
+
+        for key in keys {
+            self.join_class(key);
+        }
+
+        self.updates = 0;
+    }
+}
+
+
+/// Compute `2^e` exactly for any exponent of a positive value of `F`. A single `powi` underflows
+/// to zero for subnormal results, since its intermediate reciprocal overflows, so the exponent is
+/// split in half.
+fn pow2<F: Float>(e: i32) -> F {
+    let two = F::one() + F::one();
+    two.powi(e / 2) * two.powi(e - e / 2)
+}
+
+
+/// The level of a positive weight: the exponent `e` such that `2^e <= w < 2^(e + 1)`.
+fn level_of<F: Float>(w: F) -> i32 {
+    let (mantissa, exponent, _) = w.integer_decode();
+    exponent as i32 + 63 - mantissa.leading_zeros() as i32
+}
+
+
+impl<T, F> Sample<T> for DynamicAliasTable<T, F>
+    where T: Clone,
+          F: AliasWeight + Float
+{
+    fn sample<R: Rng>(&mut self, rng: &mut R) -> T {
+        self.pick(rng).clone()
+    }
+}
+
+impl<T, F> IndependentSample<T> for DynamicAliasTable<T, F>
+    where T: Clone,
+          F: AliasWeight + Float
+{
+    fn ind_sample<R: Rng>(&self, rng: &mut R) -> T {
+        self.pick(rng).clone()
+    }
+}
+
+
+impl<'a, T: 'a, F, R> Iterator for DynamicAliasIterator<'a, T, F, R>
+    where F: AliasWeight + Float,
+          R: Rng
+{
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.table.pick(&mut self.rng))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (usize::MAX, None)
+    }
+}
@@ -1,21 +1,83 @@
 //! # Walker-Vose Alias Method
 //! A simple implementation of alias tables using the Walker-Vose method.
 
+#[cfg(feature = "csv")]
+extern crate csv;
+#[cfg(feature = "ndarray")]
+extern crate ndarray;
+#[cfg(feature = "num-rational")]
+extern crate num_rational;
 extern crate num_traits;
 extern crate rand;
+#[cfg(feature = "rayon")]
+extern crate rayon;
+#[cfg(feature = "serde")]
+extern crate serde;
 
+use std::cmp::{self, Ordering};
+use std::collections::{BTreeMap, BinaryHeap, HashMap};
+use std::convert::TryFrom;
+use std::error::Error;
 use std::fmt;
-use std::iter::{FromIterator, Sum};
+use std::mem;
+use std::sync::OnceLock;
+use std::hash::Hash;
+#[cfg(feature = "csv")]
+use std::io::Read;
+use std::iter::{self, Cloned, FromIterator};
+use std::ops::{Add, Div, Index, Sub};
+#[cfg(feature = "csv")]
+use std::str::FromStr;
 use std::vec::Vec;
 
-use num_traits::{Float, NumCast, One, Zero};
+#[cfg(feature = "ndarray")]
+use ndarray::ArrayView1;
+#[cfg(feature = "num-rational")]
+use num_rational::Ratio;
+use num_traits::{Float, One, ToPrimitive, Zero};
 
 use rand::Rng;
-use rand::distributions::range::{Range, SampleRange};
-use rand::distributions::IndependentSample;
+use rand::distributions::range::Range;
+use rand::distributions::{Gamma, IndependentSample, Sample};
 
+#[cfg(feature = "rayon")]
+use rand::{SeedableRng, XorShiftRng};
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
 
-#[derive(Debug)]
+mod bag;
+mod batch;
+#[cfg(feature = "serde")]
+mod config;
+mod dynamic;
+mod pity;
+mod qmc;
+mod static_table;
+
+pub use bag::WeightedBag;
+pub use batch::BatchIndexTable;
+#[cfg(feature = "serde")]
+pub use config::{WeightedEntry, WeightedList};
+pub use dynamic::{DynamicAliasIterator, DynamicAliasTable};
+pub use pity::PityTable;
+pub use qmc::HaltonSequence;
+pub use static_table::StaticAliasTable;
+
+
+/// Construct an `AliasTable` from a literal list of `value => weight` pairs, such as
+/// `alias_table!{ "sword" => 1.0, "shield" => 2.5, "gem" => 0.1 }`. Panics if the weights do not
+/// form a valid distribution, in the same way as collecting into an `AliasTable` does.
+#[macro_export]
+macro_rules! alias_table {
+    ($($value:expr => $weight:expr),+ $(,)*) => {
+        <$crate::AliasTable<_, _> as ::std::iter::FromIterator<_>>::from_iter(
+            vec![$(($value, $weight)),+]
+        )
+    };
+}
+
+
+#[derive(Clone, Debug)]
 enum AliasEntry<F> {
     Aliased {
         threshold: F,
@@ -27,13 +89,373 @@ enum AliasEntry<F> {
 use AliasEntry::*;
 
 
+/// An error produced when a set of weights cannot be turned into an alias table.
+#[derive(Clone, Debug, PartialEq)]
+pub enum AliasTableError {
+    /// The distribution has no entries to pick from.
+    Empty,
+    /// One of the weights is infinite or NaN.
+    NonFiniteWeight {
+        /// The index of the offending weight.
+        index: usize,
+        /// The offending weight.
+        value: f64,
+    },
+    /// One of the weights is negative.
+    NegativeWeight {
+        /// The index of the offending weight.
+        index: usize,
+        /// The offending weight.
+        value: f64,
+    },
+    /// The weights sum to zero, so there is nothing to normalize against.
+    ZeroTotalWeight,
+    /// A number, such as the entry count, could not be represented by the weight type `F`.
+    CastFailure,
+    /// The number of values and the number of weights differ.
+    LengthMismatch {
+        /// The number of values given.
+        values: usize,
+        /// The number of weights given.
+        weights: usize,
+    },
+    /// The weights sum to more than the weight type can represent.
+    WeightOverflow,
+    /// A cumulative distribution decreases at the given index.
+    NonMonotoneCdf {
+        /// The index of the first entry which is smaller than its predecessor.
+        index: usize,
+    },
+    /// An entry has a weight of zero, and zero weights were configured to be rejected.
+    ZeroWeight {
+        /// The index of the first zero-weight entry.
+        index: usize,
+    },
+    /// A construction parameter, such as a temperature, is outside of its valid range.
+    InvalidParameter {
+        /// The name of the offending parameter.
+        name: &'static str,
+    },
+    /// Probabilities which were expected to sum to one don't, within the given tolerance.
+    NotNormalized {
+        /// The sum of the probabilities.
+        sum: f64,
+    },
+    /// A record of a CSV file could not be read.
+    CsvRead {
+        /// The line the record starts on, if known.
+        line: Option<u64>,
+        /// A description of what went wrong.
+        message: String,
+    },
+    /// A field of a CSV record is missing, or could not be parsed.
+    CsvField {
+        /// The line the record starts on.
+        line: u64,
+        /// The index of the offending column.
+        column: usize,
+    },
+    /// An operation needs the weights a table was built from, but the table wasn't built with
+    /// `AliasTableBuilder::retain_weights`.
+    WeightsNotRetained,
+}
+
+impl fmt::Display for AliasTableError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            AliasTableError::Empty => write!(fmt, "cannot build an alias table with no entries"),
+            AliasTableError::NonFiniteWeight { index, value } => {
+                write!(fmt, "weight {} at index {} is not finite", value, index)
+            }
+            AliasTableError::NegativeWeight { index, value } => {
+                write!(fmt, "weight {} at index {} is negative", value, index)
+            }
+            AliasTableError::ZeroTotalWeight => write!(fmt, "alias table weights sum to zero"),
+            AliasTableError::CastFailure => {
+                write!(fmt, "number cannot be represented by the weight type")
+            }
+            AliasTableError::LengthMismatch { values, weights } => {
+                write!(fmt, "got {} values but {} weights", values, weights)
+            }
+            AliasTableError::WeightOverflow => write!(fmt, "alias table weights overflow"),
+            AliasTableError::NonMonotoneCdf { index } => {
+                write!(fmt, "cumulative distribution decreases at index {}", index)
+            }
+            AliasTableError::ZeroWeight { index } => write!(fmt, "entry {} has zero weight", index),
+            AliasTableError::InvalidParameter { name } => {
+                write!(fmt, "invalid value for parameter `{}`", name)
+            }
+            AliasTableError::NotNormalized { sum } => {
+                write!(fmt, "probabilities sum to {} rather than one", sum)
+            }
+            AliasTableError::CsvRead { line: Some(line), ref message } => {
+                write!(fmt, "failed to read CSV record on line {}: {}", line, message)
+            }
+            AliasTableError::CsvRead { line: None, ref message } => {
+                write!(fmt, "failed to read CSV record: {}", message)
+            }
+            AliasTableError::CsvField { line, column } => {
+                write!(fmt, "missing or invalid field in column {} on line {}", column, line)
+            }
+            AliasTableError::WeightsNotRetained => {
+                write!(fmt, "the alias table was not built with retained weights")
+            }
+        }
+    }
+}
+
+impl Error for AliasTableError {}
+
+
+/// A type which the thresholds of an alias table can be stored in. Picking from a bucket compares
+/// its threshold against a uniform sample drawn below the table's scale, which is one for tables
+/// built from floating point weights, and the total weight for tables built from integer weights.
+pub trait AliasThreshold: Copy + PartialOrd {
+    /// Draw a uniform sample from `[0, scale)`.
+    fn sample_below<R: Rng>(scale: Self, rng: &mut R) -> Self;
+}
+
+/// The operations alias table construction needs from a weight type. This is implemented for
+/// `f32` and `f64`, and can be implemented for other numeric types, such as fixed-point numbers,
+/// so that they can be used as weights. Weights are normalized so that the thresholds of the
+/// table lie in `[zero, one)`. Compensated summation needs a type which can represent negative
+/// values, and is skipped for types which can't; see `is_signed_weight`.
+pub trait AliasWeight: AliasThreshold + Zero + One + Sub<Output = Self> + Div<Output = Self> {
+    /// Convert an integer, such as the number of entries in a table, into a weight, returning
+    /// `None` if it can't be represented.
+    fn from_u64(n: u64) -> Option<Self>;
+
+    /// Whether this is a finite number. Weights which aren't are rejected during construction.
+    fn is_finite_weight(self) -> bool {
+        true
+    }
+
+    /// The absolute value of this weight, used for compensated summation.
+    fn abs_weight(self) -> Self {
+        if self < Self::zero() { Self::zero() - self } else { self }
+    }
+
+    /// Whether this type can represent negative values. The correction terms of compensated
+    /// summation can be negative, so `AliasTableBuilder::compensated_summation` has no effect
+    /// for types which return `false`, such as unsigned fixed-point numbers. Defaults to `false`.
+    fn is_signed_weight() -> bool {
+        false
+    }
+
+    /// Convert this weight to an `f64`, for reporting invalid weights in errors.
+    fn weight_to_f64(self) -> f64;
+}
+
+macro_rules! float_weight_impl {
+    ($ty:ty) => {
+        impl AliasThreshold for $ty {
+            fn sample_below<R: Rng>(scale: Self, rng: &mut R) -> Self {
+                scale * rng.gen::<$ty>()
+            }
+        }
+
+        impl AliasWeight for $ty {
+            fn from_u64(n: u64) -> Option<Self> {
+                Some(n as $ty)
+            }
+
+            fn is_finite_weight(self) -> bool {
+                self.is_finite()
+            }
+
+            fn abs_weight(self) -> Self {
+                self.abs()
+            }
+
+            fn is_signed_weight() -> bool {
+                true
+            }
+
+            fn weight_to_f64(self) -> f64 {
+                self as f64
+            }
+        }
+    }
+}
+
+float_weight_impl! { f32 }
+float_weight_impl! { f64 }
+
+impl AliasThreshold for u64 {
+    fn sample_below<R: Rng>(scale: Self, rng: &mut R) -> Self {
+        Range::new(0, scale).ind_sample(rng)
+    }
+}
+
+
 /// An alias table, which uses floating point probabilities of type `F` and table entries of type
 /// `T`.
+#[derive(Clone)]
 pub struct AliasTable<T, F> {
-    table: Vec<AliasEntry<F>>,
+    indices: AliasIndexTable<F>,
     objs: Vec<T>,
+    // For tables whose values were reordered by `AliasTableBuilder::hot_first`, the index each
+    // value was pushed at.
+    order: Option<Vec<usize>>,
+    // The weights the table was built from, for tables built with
+    // `AliasTableBuilder::retain_weights`.
+    weights: Option<Vec<F>>,
+}
+
+/// An alias table over the indices `0..n`, which uses floating point probabilities of type `F`.
+/// This is an `AliasTable` without any stored values, for when the data being picked from lives
+/// elsewhere.
+#[derive(Clone)]
+pub struct AliasIndexTable<F> {
+    table: Vec<AliasEntry<F>>,
     range: Range<usize>,
-    float: Range<F>,
+    // The threshold of a bucket which always picks its own value: one for floating point tables,
+    // and the total weight for integer tables.
+    scale: F,
+    // The probability of every index, recovered from the buckets the first time it's needed.
+    probabilities: OnceLock<Vec<F>>,
+}
+
+// Tables hold no interior mutability besides their `OnceLock`, so they can be shared between
+// threads whenever their values and weights can.
+const _: () = {
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[allow(dead_code)]
+    fn tables_are_send_sync<T: Send + Sync, F: Send + Sync>() {
+        assert_send_sync::<AliasTable<T, F>>();
+        assert_send_sync::<AliasIndexTable<F>>();
+        assert_send_sync::<DynamicAliasTable<T, F>>();
+    }
+};
+
+/// A builder for assembling an alias table one entry at a time.
+#[derive(Clone, Debug)]
+pub struct AliasTableBuilder<T, F> {
+    objs: Vec<T>,
+    weights: Vec<F>,
+    options: BuildOptions,
+}
+
+/// How construction treats entries whose weight is exactly zero.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ZeroWeights {
+    /// Keep zero-weight entries, so that indices line up with the input, but guarantee that they
+    /// are never picked. This is the default.
+    #[default]
+    Keep,
+    /// Remove zero-weight entries from the table entirely.
+    Drop,
+    /// Fail construction with `AliasTableError::ZeroWeight`.
+    Reject,
+}
+
+/// Options controlling how the weights of an alias table are processed during construction.
+#[derive(Clone, Copy, Debug, Default)]
+struct BuildOptions {
+    compensated: bool,
+    zero_weights: ZeroWeights,
+    canonical: bool,
+    hot_first: bool,
+    retain_weights: bool,
+}
+
+/// The heap memory used by an alias table, in bytes. This counts the space allocated for the
+/// table and the stored values, but not any heap memory owned by the values themselves.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MemoryUsage {
+    /// Bytes allocated for the table's buckets.
+    pub table: usize,
+    /// Bytes allocated for the stored values.
+    pub objs: usize,
+}
+
+/// The number of buckets of each kind in an alias table, for diagnostics.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EntryCounts {
+    /// Buckets which are split between their own value and an alias.
+    pub aliased: usize,
+    /// Buckets which always pick their own value.
+    pub unaliased: usize,
+}
+
+/// The result of checking the structure of an alias table with `AliasIndexTable::validate`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ValidationReport {
+    /// The number of buckets checked.
+    pub buckets: usize,
+    /// The sum of the probabilities recovered from the buckets, ignoring any indices which are
+    /// out of range.
+    pub sum: f64,
+    /// Every problem found, in the order of the buckets.
+    pub violations: Vec<Violation>,
+}
+
+impl ValidationReport {
+    /// Whether the table passed every check.
+    pub fn is_valid(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+/// The result of a chi-square goodness-of-fit test, from `AliasIndexTable::chi_square_test`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ChiSquareTest {
+    /// Pearson's chi-square statistic for the observed counts.
+    pub statistic: f64,
+    /// The number of degrees of freedom: one less than the number of indices which can be picked.
+    pub degrees_of_freedom: usize,
+    /// The probability of a statistic at least this large if the picks follow the distribution.
+    pub p_value: f64,
+}
+
+/// A broken invariant of an alias table, found by `AliasIndexTable::validate`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Violation {
+    /// A bucket's threshold is not between zero and one (or the total weight, for integer
+    /// tables), or is NaN.
+    ThresholdOutOfRange {
+        /// The index of the offending bucket.
+        bucket: usize,
+        /// The bucket's threshold, as a fraction of the table's scale.
+        threshold: f64,
+    },
+    /// A bucket refers to an index which is outside of the table.
+    IndexOutOfRange {
+        /// The index of the offending bucket.
+        bucket: usize,
+        /// The offending index.
+        index: usize,
+    },
+    /// The probabilities recovered from the buckets don't sum to one.
+    NotNormalized {
+        /// The sum of the probabilities.
+        sum: f64,
+    },
+    /// An index is not referred to by any bucket, so the table can never pick it.
+    Unreachable {
+        /// The unreachable index.
+        index: usize,
+    },
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Violation::ThresholdOutOfRange { bucket, threshold } => {
+                write!(fmt, "bucket {} has threshold {} outside of [0, 1]", bucket, threshold)
+            }
+            Violation::IndexOutOfRange { bucket, index } => {
+                write!(fmt, "bucket {} refers to out of range index {}", bucket, index)
+            }
+            Violation::NotNormalized { sum } => {
+                write!(fmt, "probabilities sum to {} rather than one", sum)
+            }
+            Violation::Unreachable { index } => {
+                write!(fmt, "index {} is not referred to by any bucket", index)
+            }
+        }
+    }
 }
 
 /// An iterator for an alias table.
@@ -45,120 +467,4422 @@ pub struct AliasTableIterator<'a, T: 'a, F: 'a, R>
     table: &'a AliasTable<T, F>
 }
 
+/// An iterator for an index table, picking random indices from it.
+#[derive(Clone)]
+pub struct AliasIndexIterator<'a, F: 'a, R>
+    where R: Rng + Sized
+{
+    rng: R,
+    table: &'a AliasIndexTable<F>,
+}
+
+/// An iterator for an alias table which never picks the same element twice in a row.
+#[derive(Clone)]
+pub struct NoRepeatIterator<'a, T: 'a, F: 'a, R>
+    where R: Rng + Sized
+{
+    rng: R,
+    table: &'a AliasTable<T, F>,
+    last: Option<usize>,
+}
+
+/// An iterator which owns an alias table, picking random elements from it by value.
+pub struct AliasTableIntoIterator<T, F, R>
+    where R: Rng + Sized
+{
+    rng: R,
+    table: AliasTable<T, F>,
+}
+
+/// An iterator for an alias table which stops after a fixed number of picks.
+#[derive(Clone)]
+pub struct AliasTableBoundedIterator<'a, T: 'a, F: 'a, R>
+    where R: Rng + Sized
+{
+    rng: R,
+    table: &'a AliasTable<T, F>,
+    remaining: usize,
+}
+
 
 impl<T, F> fmt::Debug for AliasTable<T, F>
+    where T: fmt::Debug,
+          F: fmt::Debug
+{
+    /// Print every bucket of the table as `value (p=threshold, alias -> alias)`, where the
+    /// threshold is the chance of keeping the bucket's own value (out of the total weight, for
+    /// integer tables), or as a bare value for buckets which always keep it.
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let buckets: Vec<_> = self.indices
+            .table
+            .iter()
+            .map(|entry| DebugBucket(entry, &self.objs))
+            .collect();
+        fmt.debug_struct("AliasTable").field("table", &buckets).finish()
+    }
+}
+
+/// A bucket of an `AliasTable`, printed with the values it picks.
+struct DebugBucket<'a, T: 'a, F: 'a>(&'a AliasEntry<F>, &'a [T]);
+
+impl<'a, T, F> fmt::Debug for DebugBucket<'a, T, F>
+    where T: fmt::Debug,
+          F: fmt::Debug
+{
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self.0 {
+            Aliased { ref threshold, value, alias } => {
+                write!(fmt, "{:?} (p={:?}, alias -> {:?})", self.1[value], threshold, self.1[alias])
+            }
+            Unaliased(value) => write!(fmt, "{:?}", self.1[value]),
+        }
+    }
+}
+
+impl<T, F> fmt::Display for AliasTable<T, F>
+    where T: fmt::Display,
+          F: AliasThreshold + Float + fmt::Display
+{
+    /// List every value of the table with its probability as recovered by `probabilities`, one
+    /// per line, from the most to the least likely. A precision given in the format string is
+    /// applied to the probabilities.
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let ps = self.indices.cached_probabilities();
+        let mut order: Vec<usize> = (0..ps.len()).collect();
+        order.sort_by(|&a, &b| ps[b].partial_cmp(&ps[a]).unwrap_or(Ordering::Equal));
+
+        for (line, idx) in order.into_iter().enumerate() {
+            if line > 0 {
+                writeln!(fmt)?;
+            }
+
+            match fmt.precision() {
+                Some(precision) => write!(fmt, "{}: {:.*}", self.objs[idx], precision, ps[idx])?,
+                None => write!(fmt, "{}: {}", self.objs[idx], ps[idx])?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<F> fmt::Debug for AliasIndexTable<F>
     where F: fmt::Debug
 {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
-        write!(fmt, "AliasTable {{ table: {:?} }}", self.table)
+        write!(fmt, "AliasIndexTable {{ table: {:?} }}", self.table)
     }
 }
 
 
 impl<T, F> AliasTable<T, F>
-    where F: PartialOrd + SampleRange
+    where F: AliasThreshold
 {
-    /// Pick a random element from the distribution. Samples from the RNG using `ind_sample` only.
+    /// Pick a random element from the distribution. Samples from the RNG using `ind_sample` and
+    /// `AliasThreshold::sample_below` only.
     pub fn pick<'a, R: Rng>(&'a self, rng: &mut R) -> &'a T {
+        &self.objs[self.pick_index(rng)]
+    }
+
+    /// Pick random elements from the distribution until one satisfies `predicate`, giving up
+    /// after `max_attempts` picks. Returns the element, or `None` if every attempt was rejected,
+    /// along with the number of picks made.
+    pub fn sample_until<R, P>(&self,
+                              rng: &mut R,
+                              mut predicate: P,
+                              max_attempts: usize)
+                              -> (Option<&T>, usize)
+        where R: Rng,
+              P: FnMut(&T) -> bool
+    {
+        for attempt in 1..=max_attempts {
+            let obj = self.pick(rng);
+
+            if predicate(obj) {
+                return (Some(obj), attempt);
+            }
+        }
+
+        (None, max_attempts)
+    }
+
+    /// Pick a random element from the distribution, returning both its index in the table and a
+    /// reference to it.
+    pub fn pick_entry<R: Rng>(&self, rng: &mut R) -> (usize, &T) {
+        let idx = self.pick_index(rng);
+        (idx, &self.objs[idx])
+    }
+
+    /// Pick a random element from the distribution, returning a mutable reference to it. The
+    /// weights are unaffected by changes to the element.
+    pub fn pick_mut<R: Rng>(&mut self, rng: &mut R) -> &mut T {
+        let idx = self.pick_index(rng);
+        &mut self.objs[idx]
+    }
+
+    /// Pick a random element from the distribution, returning its index in the table instead of
+    /// a reference to it. Consumes the same random numbers as `pick`, so the two agree when given
+    /// identically seeded RNGs.
+    pub fn pick_index<R: Rng>(&self, rng: &mut R) -> usize {
+        self.indices.pick(rng)
+    }
+
+    /// Fill a buffer with random elements from the distribution, picking one for every slot.
+    pub fn fill<'a, R: Rng>(&'a self, rng: &mut R, out: &mut [&'a T]) {
+        for slot in out {
+            *slot = self.pick(rng);
+        }
+    }
+
+    /// Fill a buffer with the indices of random elements from the distribution, like calling
+    /// `pick_index` once for every slot.
+    pub fn fill_indices<R: Rng>(&self, rng: &mut R, out: &mut [usize]) {
+        self.indices.fill_indices(rng, out);
+    }
+
+    /// Pick `n` random elements from the distribution, returning them in a vector.
+    pub fn sample_n<R: Rng>(&self, rng: &mut R, n: usize) -> Vec<&T> {
+        (0..n).map(|_| self.pick(rng)).collect()
+    }
+
+    /// Given an RNG, produce an iterator that picks random element from the distribution by
+    /// calling `pick` repeatedly with the given RNG.
+    pub fn iter<R: Rng>(&self, rng: R) -> AliasTableIterator<'_, T, F, R> {
+        AliasTableIterator {
+            rng,
+            table: self
+        }
+    }
+
+    /// Given an RNG, produce an iterator that picks random indices from the distribution by
+    /// calling `pick_index` repeatedly with the given RNG. The iterator only borrows the table's
+    /// `AliasIndexTable`, and its type doesn't mention the type of the values.
+    pub fn indices<R: Rng>(&self, rng: R) -> AliasIndexIterator<'_, F, R> {
+        self.indices.iter(rng)
+    }
+
+    /// Consume the table, producing an iterator which owns it and the given RNG and picks random
+    /// elements from it by value. Unlike `iter`, the iterator doesn't borrow anything, so it can
+    /// be stored or sent to another thread freely.
+    pub fn into_sample_iter<R: Rng>(self, rng: R) -> AliasTableIntoIterator<T, F, R>
+        where T: Clone
+    {
+        AliasTableIntoIterator { rng, table: self }
+    }
+
+    /// Given an RNG, produce an iterator that picks exactly `n` random elements from the
+    /// distribution, like `iter` but with an exact length.
+    pub fn sample_iter_n<R: Rng>(&self,
+                                 rng: R,
+                                 n: usize)
+                                 -> AliasTableBoundedIterator<'_, T, F, R> {
+        AliasTableBoundedIterator {
+            rng,
+            table: self,
+            remaining: n,
+        }
+    }
+
+    /// Pick a random element from the distribution, returning a clone of it rather than a
+    /// reference.
+    pub fn sample<R: Rng>(&self, rng: &mut R) -> T
+        where T: Clone
+    {
+        self.pick(rng).clone()
+    }
+
+    /// Given an RNG, produce an iterator that picks random elements from the distribution by
+    /// value, like `iter` but cloning each element.
+    pub fn sample_iter<R: Rng>(&self, rng: R) -> Cloned<AliasTableIterator<'_, T, F, R>>
+        where T: Clone
+    {
+        self.iter(rng).cloned()
+    }
+}
+
+impl<F> AliasIndexTable<F>
+    where F: AliasThreshold
+{
+    /// Pick a random index from the distribution. Samples from the RNG using `ind_sample` and
+    /// `AliasThreshold::sample_below` only.
+    pub fn pick<R: Rng>(&self, rng: &mut R) -> usize {
         let idx = self.range.ind_sample(rng);
         let entry = &self.table[idx];
         match *entry {
             Aliased { ref threshold, value, alias } => {
-                if &self.float.ind_sample(rng) < threshold {
-                    &self.objs[value]
+                if &F::sample_below(self.scale, rng) < threshold {
+                    value
                 } else {
-                    &self.objs[alias]
+                    alias
                 }
             }
-            Unaliased(idx) => &self.objs[idx],
+            Unaliased(idx) => idx,
         }
     }
 
-    /// Given an RNG, produce an iterator that picks random element from the distribution by
+    /// Fill a buffer with random indices from the distribution, picking one for every slot.
+    pub fn fill_indices<R: Rng>(&self, rng: &mut R, out: &mut [usize]) {
+        for slot in out {
+            *slot = self.pick(rng);
+        }
+    }
+
+    /// Given an RNG, produce an iterator that picks random indices from the distribution by
     /// calling `pick` repeatedly with the given RNG.
-    pub fn iter<R: Rng>(&self, rng: R) -> AliasTableIterator<T, F, R> {
-        AliasTableIterator {
-            rng: rng,
-            table: self
+    pub fn iter<R: Rng>(&self, rng: R) -> AliasIndexIterator<'_, F, R> {
+        AliasIndexIterator {
+            rng,
+            table: self,
         }
     }
 }
 
-impl<'a, T, F: 'a> FromIterator<(T, F)> for AliasTable<T, F>
-    where F: Float + NumCast + One + SampleRange + Sum<F> + Zero
+impl<T, F> AliasTable<T, F>
+    where F: AliasThreshold + Float
 {
-    /// Construct an alias table from an iterator. Expects a tuple, where the left-hand element is
-    /// the distribution's value, and the right-hand element is the value's weight in the distribution.
-    fn from_iter<I: IntoIterator<Item = (T, F)>>(iter: I) -> Self {
-        let (objs, ps): (Vec<_>, Vec<_>) = iter.into_iter().unzip();
-        let psum: F = ps.iter().cloned().sum();
+    /// Pick an element deterministically from a single uniform variate in `[0, 1)`, as described
+    /// by `AliasIndexTable::pick_from_uniform`.
+    pub fn pick_from_uniform(&self, u: F) -> &T {
+        &self.objs[self.indices.pick_from_uniform(u)]
+    }
 
-        let pn = F::from(ps.len())
-            .expect("Error casting usize to generic parameter F of AliasTable<T, F>");
-        let pcoeff = pn / psum;
+    /// Map an iterator of points in `[0, 1)^2` to elements of the table, as described by
+    /// `AliasIndexTable::pick_points`.
+    pub fn pick_points<'a, I>(&'a self, points: I) -> impl Iterator<Item = &'a T> + 'a
+        where I: IntoIterator<Item = (F, F)>,
+              I::IntoIter: 'a
+    {
+        self.indices.pick_points(points).map(move |idx| &self.objs[idx])
+    }
 
-        let (mut small, mut large): (Vec<_>, Vec<_>) =
-            ps.into_iter().map(|p| pcoeff * p).enumerate().partition(|&(_, p)| p < F::one());
-        let mut table = Vec::new();
+    /// Pick an antithetic pair of elements at random, as described by
+    /// `AliasIndexTable::pick_antithetic`.
+    pub fn pick_antithetic<R: Rng>(&self, rng: &mut R) -> (&T, &T) {
+        let (a, b) = self.indices.pick_antithetic(rng);
+        (&self.objs[a], &self.objs[b])
+    }
 
+    /// Given an RNG, produce an iterator of antithetic pairs of elements, by calling
+    /// `pick_antithetic` repeatedly with the given RNG.
+    pub fn antithetic_iter<'a, R>(&'a self, mut rng: R) -> impl Iterator<Item = (&'a T, &'a T)>
+        where R: Rng + 'a
+    {
+        iter::repeat_with(move || self.pick_antithetic(&mut rng))
+    }
 
-        while !(small.is_empty() || large.is_empty()) {
-            let (l, p_l) = small.pop().unwrap();
-            let (g, p_g) = large.pop().unwrap();
+    /// Pick a random element from the distribution, also returning the probability with which it
+    /// is picked, as described by `AliasIndexTable::pick_with_prob`.
+    pub fn pick_with_prob<R: Rng>(&self, rng: &mut R) -> (&T, F) {
+        let (idx, p) = self.indices.pick_with_prob(rng);
+        (&self.objs[idx], p)
+    }
 
-            table.push(Aliased {
-                threshold: p_l,
-                value: l,
-                alias: g,
-            });
+    /// Pick a random element by the Gumbel-max trick, as described by
+    /// `AliasIndexTable::pick_gumbel`.
+    pub fn pick_gumbel<R: Rng>(&self, rng: &mut R) -> (&T, F) {
+        let (idx, key) = self.indices.pick_gumbel(rng);
+        (&self.objs[idx], key)
+    }
 
-            let p_g = (p_g + p_l) - F::one();
+    /// Pick `k` distinct elements by the Gumbel-top-k trick, as described by
+    /// `AliasIndexTable::gumbel_top_k`.
+    pub fn gumbel_top_k<R: Rng>(&self, rng: &mut R, k: usize) -> Vec<(&T, F)> {
+        self.indices
+            .gumbel_top_k(rng, k)
+            .into_iter()
+            .map(|(idx, key)| (&self.objs[idx], key))
+            .collect()
+    }
 
-            if p_g < F::one() {
-                    &mut small
-                } else {
-                    &mut large
-                }
-                .push((g, p_g));
-        }
+    /// Recover the probability with which every element is picked, in the order of the table, as
+    /// described by `AliasIndexTable::probabilities`.
+    pub fn probabilities(&self) -> Vec<F> {
+        self.indices.probabilities()
+    }
 
-        table.extend(large.iter().map(|&(g, _)| Unaliased(g)));
+    /// The most likely element of the table, as described by `AliasIndexTable::mode`.
+    pub fn mode(&self) -> &T {
+        &self.objs[self.indices.mode()]
+    }
 
-        table.extend(small.iter().map(|&(l, _)| Unaliased(l)));
+    /// The `k` most likely elements of the table with their probabilities, as described by
+    /// `AliasIndexTable::top_n`.
+    pub fn top_n(&self, k: usize) -> Vec<(&T, F)> {
+        self.indices.top_n(k).into_iter().map(|(idx, p)| (&self.objs[idx], p)).collect()
+    }
 
-        AliasTable {
-            range: Range::new(0, table.len()),
-            float: Range::new(F::zero(), F::one()),
-            table: table,
-            objs: objs,
+    /// The `q`th quantile of the table's values, treating a pick as a discrete random variable:
+    /// the smallest value `x` which can be picked such that a pick is no greater than `x` with
+    /// probability at least `q`. Computed exactly from the probabilities recovered by
+    /// `probabilities`, apart from rounding error. Returns `None` if `q` is not between zero and
+    /// one.
+    pub fn quantile(&self, q: F) -> Option<&T>
+        where T: PartialOrd
+    {
+        if !(q >= F::zero() && q <= F::one()) {
+            return None;
         }
+
+        let mut support: Vec<_> = self.support().collect();
+        support.sort_by(|a, b| a.0.partial_cmp(b.0).unwrap_or(Ordering::Equal));
+
+        let mut sum = F::zero();
+        let last = support.len() - 1;
+
+        support.iter().enumerate().find(|&(i, &(_, p))| {
+            sum = sum + p;
+            sum >= q || i == last
+        }).map(|(_, &(obj, _))| obj)
     }
-}
 
-impl<'a, T: 'a, F, R> Iterator for AliasTableIterator<'a, T, F, R>
-    where F: PartialOrd + SampleRange,
-          R: Rng
-{
-    type Item = &'a T;
+    /// Render the buckets of the table as a Graphviz DOT graph, as described by
+    /// `AliasIndexTable::to_dot`, labelling each value with its `Display` form.
+    pub fn to_dot(&self) -> String
+        where T: fmt::Display,
+              F: fmt::Display
+    {
+        self.indices.render_dot(|idx| self.objs[idx].to_string())
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        Some(self.table.pick(&mut self.rng))
+    /// Render the buckets of the table as a text chart, as described by
+    /// `AliasIndexTable::to_ascii_chart`, labelling each value with its `Display` form.
+    pub fn to_ascii_chart(&self) -> String
+        where T: fmt::Display,
+              F: fmt::Display
+    {
+        self.indices.render_ascii_chart(|idx| self.objs[idx].to_string())
     }
 
-    fn size_hint(&self) -> (usize, Option<usize>) {
-        (std::usize::MAX, None)
+    /// The values of the table together with its cumulative distribution, as described by
+    /// `AliasIndexTable::to_cdf`. This is the inverse of `from_cdf`.
+    pub fn to_cdf(&self) -> (&[T], Vec<F>) {
+        (&self.objs, self.indices.to_cdf())
     }
-}
 
-impl<'a, T, F> IntoIterator for &'a AliasTable<T, F>
-    where F: Sized + PartialOrd + SampleRange
-{
-    type Item = &'a T;
-    type IntoIter = AliasTableIterator<'a, T, F, rand::ThreadRng>;
+    /// Take the table apart into its values and the probabilities recovered by `probabilities`,
+    /// both in the order of the table.
+    pub fn into_parts(self) -> (Vec<T>, Vec<F>) {
+        let ps = self.probabilities();
+        (self.objs, ps)
+    }
 
-    /// Produces an iterator that picks random element from the distribution by calling
-    /// calling `pick` repeatedly with the thread's RNG.
+    /// Check the structural invariants of the table, as described by `AliasIndexTable::validate`.
+    pub fn validate(&self) -> ValidationReport {
+        self.indices.validate()
+    }
+
+    /// Iterate over the support of the distribution: every element with a nonzero probability,
+    /// in the order of the table, along with its probability as recovered by `probabilities`.
+    pub fn support(&self) -> impl Iterator<Item = (&T, F)> + '_ {
+        self.objs
+            .iter()
+            .zip(self.indices.cached_probabilities().iter().cloned())
+            .filter(|&(_, p)| p > F::zero())
+    }
+
+    /// The Shannon entropy of the distribution in nats, as described by
+    /// `AliasIndexTable::entropy`.
+    pub fn entropy(&self) -> F {
+        self.indices.entropy()
+    }
+
+    /// The perplexity of the distribution, as described by `AliasIndexTable::perplexity`.
+    pub fn perplexity(&self) -> F {
+        self.indices.perplexity()
+    }
+
+    /// The effective number of elements of the distribution, as described by
+    /// `AliasIndexTable::effective_support_size`.
+    pub fn effective_support_size(&self) -> F {
+        self.indices.effective_support_size()
+    }
+
+    /// The Gini coefficient of the probabilities of the table's elements, as described by
+    /// `AliasIndexTable::gini`.
+    pub fn gini(&self) -> F {
+        self.indices.gini()
+    }
+
+    /// The Herfindahl-Hirschman index of the probabilities of the table's elements, as described
+    /// by `AliasIndexTable::hhi`.
+    pub fn hhi(&self) -> F {
+        self.indices.hhi()
+    }
+
+    /// The Kullback-Leibler divergence of `other` from this table in nats, matching elements by
+    /// index, as described by `AliasIndexTable::kl_divergence`.
+    pub fn kl_divergence(&self, other: &AliasTable<T, F>) -> F {
+        self.indices.kl_divergence(&other.indices)
+    }
+
+    /// The Kullback-Leibler divergence of `other` from this table in nats, matching elements by
+    /// value rather than by index, so the tables may store their values in different orders. The
+    /// probabilities of repeated values are added together. The divergence is infinite if this
+    /// table can pick a value which `other` can't.
+    pub fn kl_divergence_by_value(&self, other: &AliasTable<T, F>) -> F
+        where T: Eq + Hash
+    {
+        let qs = other.value_probabilities();
+        self.value_probabilities()
+            .into_iter()
+            .map(|(obj, p)| (p, qs.get(obj).cloned().unwrap_or_else(F::zero)))
+            .fold(F::zero(), |kl, (p, q)| kl + kl_term(p, q))
+    }
+
+    /// The total variation distance between the distributions of this table and `other`,
+    /// matching elements by index, as described by `AliasIndexTable::tv_distance`.
+    pub fn tv_distance(&self, other: &AliasTable<T, F>) -> F {
+        self.indices.tv_distance(&other.indices)
+    }
+
+    /// The total variation distance between the distributions of this table and `other`,
+    /// matching elements by value rather than by index, like `kl_divergence_by_value`.
+    pub fn tv_distance_by_value(&self, other: &AliasTable<T, F>) -> F
+        where T: Eq + Hash
+    {
+        let ps = self.value_probabilities();
+        let mut qs = other.value_probabilities();
+
+        let shared = ps.into_iter().fold(F::zero(), |sum, (obj, p)| {
+            sum + (p - qs.remove(obj).unwrap_or_else(F::zero)).abs()
+        });
+        let sum = qs.into_iter().fold(shared, |sum, (_, q)| sum + q);
+
+        sum / (F::one() + F::one())
+    }
+
+    /// Whether this table and `other` pick every element with nearly the same probability,
+    /// matching elements by index, as described by `AliasIndexTable::approx_eq`.
+    pub fn approx_eq(&self, other: &AliasTable<T, F>, epsilon: F) -> bool {
+        self.indices.approx_eq(&other.indices, epsilon)
+    }
+
+    /// The probability of every distinct value in the table.
+    fn value_probabilities(&self) -> HashMap<&T, F>
+        where T: Eq + Hash
+    {
+        let mut ps = HashMap::with_capacity(self.objs.len());
+
+        for (obj, p) in self.support() {
+            let sum = ps.entry(obj).or_insert_with(F::zero);
+            *sum = *sum + p;
+        }
+
+        ps
+    }
+
+    /// The exact expectation of `f` applied to a pick from the table, `sum(p_i * f(objs[i]))`,
+    /// computed in one pass over the support from the probabilities recovered by
+    /// `probabilities`. `f` is only called for elements with nonzero probability, so it needn't
+    /// be finite for the others.
+    pub fn expect<G>(&self, mut f: G) -> F
+        where G: FnMut(&T) -> F
+    {
+        self.support().fold(F::zero(), |sum, (obj, p)| sum + p * f(obj))
+    }
+
+    /// The exact mean of `f` applied to a pick from the table. This is the same as `expect`.
+    pub fn mean_by<G>(&self, f: G) -> F
+        where G: FnMut(&T) -> F
+    {
+        self.expect(f)
+    }
+
+    /// The exact variance of `f` applied to a pick from the table.
+    pub fn variance_by<G>(&self, mut f: G) -> F
+        where G: FnMut(&T) -> F
+    {
+        let xs: Vec<_> = self.support().map(|(obj, p)| (f(obj), p)).collect();
+        let mean = xs.iter().fold(F::zero(), |sum, &(x, p)| sum + p * x);
+        xs.iter().fold(F::zero(), |sum, &(x, p)| sum + p * (x - mean) * (x - mean))
+    }
+
+    /// The exact `k`th raw moment of `f` applied to a pick from the table, `E[f(X)^k]`.
+    pub fn moment_by<G>(&self, k: i32, mut f: G) -> F
+        where G: FnMut(&T) -> F
+    {
+        self.mean_by(|obj| f(obj).powi(k))
+    }
+
+    /// The exact mean of a pick from a table of numbers, like `mean_by` with the values
+    /// converted to `F`. Values which can't be converted are treated as NaN.
+    pub fn mean(&self) -> F
+        where T: ToPrimitive
+    {
+        self.mean_by(to_weight)
+    }
+
+    /// The exact variance of a pick from a table of numbers, like `variance_by` with the values
+    /// converted to `F`.
+    pub fn variance(&self) -> F
+        where T: ToPrimitive
+    {
+        self.variance_by(to_weight)
+    }
+
+    /// The exact `k`th raw moment of a pick from a table of numbers, like `moment_by` with the
+    /// values converted to `F`.
+    pub fn moment(&self, k: i32) -> F
+        where T: ToPrimitive
+    {
+        self.moment_by(k, to_weight)
+    }
+
+    /// Recover the probability with which the element at `index` is picked, as described by
+    /// `AliasIndexTable::probability_of`.
+    pub fn probability_of(&self, index: usize) -> Option<F> {
+        self.indices.probability_of(index)
+    }
+
+    /// Recover the probability with which an element equal to `value` is picked, adding up the
+    /// probabilities of every such element. Values which aren't in the table have probability
+    /// zero.
+    pub fn probability_of_value(&self, value: &T) -> F
+        where T: PartialEq
+    {
+        self.objs
+            .iter()
+            .enumerate()
+            .filter(|&(_, obj)| obj == value)
+            .filter_map(|(idx, _)| self.indices.probability_of(idx))
+            .fold(F::zero(), |sum, p| sum + p)
+    }
+
+    /// Pick a random element from the distribution using a single 64-bit random number, as
+    /// described by `AliasIndexTable::pick_fast`.
+    pub fn pick_fast<R: Rng>(&self, rng: &mut R) -> &T {
+        &self.objs[self.indices.pick_fast(rng)]
+    }
+
+    /// Pick `n` random elements from the distribution, with replacement, returning them in a
+    /// vector. The vector is allocated once up front, and every element is picked with
+    /// `pick_fast`, so this consumes different random numbers than `sample_n`.
+    pub fn pick_n_with_replacement<R: Rng>(&self, rng: &mut R, n: usize) -> Vec<&T> {
+        let mut picks = Vec::with_capacity(n);
+
+        for _ in 0..n {
+            picks.push(&self.objs[self.indices.pick_fast(rng)]);
+        }
+
+        picks
+    }
+
+    /// Pick an element deterministically from two uniform variates in `[0, 1)`, as described by
+    /// `AliasIndexTable::pick_with`.
+    pub fn pick_with(&self, u_index: F, u_threshold: F) -> &T {
+        &self.objs[self.indices.pick_with(u_index, u_threshold)]
+    }
+
+    /// Pick a random element whose index isn't in `excluded`, as described by
+    /// `AliasIndexTable::pick_excluding`.
+    pub fn pick_excluding<R: Rng>(&self, rng: &mut R, excluded: &[usize]) -> Option<&T> {
+        self.indices.pick_excluding(rng, excluded).map(|idx| &self.objs[idx])
+    }
+
+    /// Pick two distinct elements at random, as described by
+    /// `AliasIndexTable::pick_two_distinct`.
+    pub fn pick_two_distinct<R: Rng>(&self, rng: &mut R) -> Option<(&T, &T)> {
+        self.indices.pick_two_distinct(rng).map(|(a, b)| (&self.objs[a], &self.objs[b]))
+    }
+
+    /// Given an RNG, produce an iterator that picks random elements from the distribution, but
+    /// never the same element twice in a row: each pick after the first excludes the element
+    /// picked before it, as by `pick_excluding`. The iterator ends if the previous element was
+    /// the only one with a nonzero probability.
+    pub fn no_repeat_iter<R: Rng>(&self, rng: R) -> NoRepeatIterator<'_, T, F, R> {
+        NoRepeatIterator {
+            rng,
+            table: self,
+            last: None,
+        }
+    }
+
+    /// Pick `k` distinct elements at random, as described by `AliasIndexTable::pick_distinct`.
+    pub fn pick_distinct<R: Rng>(&self, rng: &mut R, k: usize) -> Vec<&T> {
+        self.indices.pick_distinct(rng, k).into_iter().map(|idx| &self.objs[idx]).collect()
+    }
+
+    /// Shuffle the elements of the table at random, as described by
+    /// `AliasIndexTable::weighted_shuffle`.
+    pub fn weighted_shuffle<R: Rng>(&self, rng: &mut R) -> Vec<&T> {
+        self.indices.weighted_shuffle(rng).into_iter().map(|idx| &self.objs[idx]).collect()
+    }
+
+    /// Draw `n` indices by systematic resampling, as described by
+    /// `AliasIndexTable::systematic_resample`.
+    pub fn systematic_resample<R: Rng>(&self, rng: &mut R, n: usize) -> Vec<usize> {
+        self.indices.systematic_resample(rng, n)
+    }
+
+    /// Draw `n` indices by residual resampling, as described by
+    /// `AliasIndexTable::residual_resample`.
+    pub fn residual_resample<R: Rng>(&self, rng: &mut R, n: usize) -> Vec<usize>
+        where F: AliasWeight
+    {
+        self.indices.residual_resample(rng, n)
+    }
+
+    /// Test whether picks follow the distribution's probabilities, as described by
+    /// `AliasIndexTable::chi_square_test`.
+    pub fn chi_square_test<R: Rng>(&self, rng: &mut R, n_draws: usize) -> ChiSquareTest {
+        self.indices.chi_square_test(rng, n_draws)
+    }
+
+    /// Count how many times each element is picked in `n` independent picks, as described by
+    /// `AliasIndexTable::sample_counts`. The counts are indexed in the same order as the table.
+    pub fn sample_counts<R: Rng>(&self, rng: &mut R, n: u64) -> Vec<u64> {
+        self.indices.sample_counts(rng, n)
+    }
+}
+
+impl<F> AliasIndexTable<F>
+    where F: AliasThreshold + Float
+{
+    /// Pick an index deterministically from a single uniform variate `u` in `[0, 1)`: the
+    /// integer part of `u * n` chooses the bucket, and its fractional part chooses between the
+    /// bucket's value and its alias, as by `pick_with`. A uniformly distributed `u` gives an index
+    /// following the table's distribution, so this can be used as a transform for stratified
+    /// sampling. Variates outside of `[0, 1)` are clamped into it.
+    pub fn pick_from_uniform(&self, u: F) -> usize {
+        let scaled = F::from(self.table.len()).map_or(F::zero(), |n| u * n);
+        self.pick_with(u, scaled - scaled.floor())
+    }
+
+    /// Map an iterator of points in `[0, 1)^2` to indices of the table, applying `pick_with` to
+    /// each point. Feeding this a low-discrepancy sequence, such as a `HaltonSequence`, gives
+    /// quasi-Monte Carlo sampling, whose picks cover the distribution more evenly than random
+    /// ones.
+    pub fn pick_points<'a, I>(&'a self, points: I) -> impl Iterator<Item = usize> + 'a
+        where I: IntoIterator<Item = (F, F)>,
+              I::IntoIter: 'a
+    {
+        points.into_iter().map(move |(u_index, u_threshold)| self.pick_with(u_index, u_threshold))
+    }
+
+    /// Pick an antithetic pair of indices at random: the first is picked with uniform variates
+    /// `u` and `v` as by `pick_with`, and the second with `1 - u` and `1 - v`. Each index of the
+    /// pair follows the table's distribution, but the two are negatively correlated, which reduces
+    /// the variance of estimates averaged over both.
+    pub fn pick_antithetic<R: Rng>(&self, rng: &mut R) -> (usize, usize) {
+        let u = F::sample_below(F::one(), rng);
+        let v = F::sample_below(F::one(), rng);
+
+        (self.pick_with(u, v), self.pick_with(F::one() - u, F::one() - v))
+    }
+
+    /// Given an RNG, produce an iterator of antithetic pairs of indices, by calling
+    /// `pick_antithetic` repeatedly with the given RNG.
+    pub fn antithetic_iter<'a, R>(&'a self, mut rng: R) -> impl Iterator<Item = (usize, usize)> + 'a
+        where R: Rng + 'a
+    {
+        iter::repeat_with(move || self.pick_antithetic(&mut rng))
+    }
+
+    /// Pick a random index from the distribution like `pick`, also returning the probability
+    /// with which that index is picked, as represented by the table. The probabilities of every
+    /// index are recovered from the table the first time this is called, and cached.
+    pub fn pick_with_prob<R: Rng>(&self, rng: &mut R) -> (usize, F) {
+        let idx = self.pick(rng);
+        (idx, self.cached_probabilities()[idx])
+    }
+
+    /// Pick a random index by the Gumbel-max trick: every index `i` is given the key
+    /// `ln(p_i) + g_i`, where the `g_i` are independent standard Gumbel noise, and the index with
+    /// the largest key is picked, which follows the table's distribution. Returns the picked
+    /// index along with its key. This takes linear time, so it is much slower than `pick`; it is
+    /// useful when the perturbed log-probability of the pick is needed as well.
+    pub fn pick_gumbel<R: Rng>(&self, rng: &mut R) -> (usize, F) {
+        self.gumbel_keys(rng)
+            .into_iter()
+            .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal))
+            .unwrap()
+    }
+
+    /// Pick `k` distinct indices by the Gumbel-top-k trick: every index is given a key as by
+    /// `pick_gumbel`, and the `k` indices with the largest keys are returned in order of
+    /// decreasing key, along with their keys. This samples without replacement, like
+    /// `pick_distinct`. Indices with zero probability are never picked, so fewer than `k` are
+    /// returned if fewer than `k` have a nonzero probability.
+    pub fn gumbel_top_k<R: Rng>(&self, rng: &mut R, k: usize) -> Vec<(usize, F)> {
+        let mut keys: Vec<_> = self.gumbel_keys(rng)
+            .into_iter()
+            .filter(|&(_, key)| key > F::neg_infinity())
+            .collect();
+
+        let by_key = |a: &(usize, F), b: &(usize, F)| {
+            b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal)
+        };
+
+        if k < keys.len() {
+            keys.select_nth_unstable_by(k, by_key);
+            keys.truncate(k);
+        }
+
+        keys.sort_by(by_key);
+        keys
+    }
+
+    /// Perturb the log-probability of every index with standard Gumbel noise.
+    fn gumbel_keys<R: Rng>(&self, rng: &mut R) -> Vec<(usize, F)> {
+        self.cached_probabilities()
+            .iter()
+            .map(|&p| p.ln() - (-F::sample_below(F::one(), rng).ln()).ln())
+            .enumerate()
+            .collect()
+    }
+
+    /// Pick a random index from the distribution using a single call to `Rng::next_u64`, rather
+    /// than the two random numbers `pick` uses. The random number is multiplied by the number of
+    /// buckets: the high 64 bits of the product choose the bucket, and the low 64 bits are the
+    /// uniform fraction compared against its threshold. For a table of `n` buckets, that fraction
+    /// has about `64 - log2(n)` bits of precision. This picks from the same distribution as
+    /// `pick`, but not with the same random numbers.
+    pub fn pick_fast<R: Rng>(&self, rng: &mut R) -> usize {
+        let product = rng.next_u64() as u128 * self.table.len() as u128;
+        let bucket = (product >> 64) as usize;
+
+        match self.table[bucket] {
+            Aliased { threshold, value, alias } => {
+                let frac = F::from(product as u64).unwrap_or_else(F::zero) *
+                           F::from(2.0f64.powi(-64)).unwrap_or_else(F::zero);
+
+                if frac * self.scale < threshold {
+                    value
+                } else {
+                    alias
+                }
+            }
+            Unaliased(idx) => idx,
+        }
+    }
+
+    /// Pick an index deterministically from two uniform variates in `[0, 1)`, rather than from
+    /// an RNG: `u_index` chooses the bucket, and `u_threshold` chooses between the bucket's value
+    /// and its alias. Variates outside of `[0, 1)` are clamped into it. Given independent uniform
+    /// variates, this follows the same distribution as `pick`, so it can be driven by recorded
+    /// random numbers, common random numbers shared between simulations, or quasi-random
+    /// sequences.
+    pub fn pick_with(&self, u_index: F, u_threshold: F) -> usize {
+        let last = self.table.len() - 1;
+        let bucket = if u_index.is_nan() || u_index <= F::zero() {
+            0
+        } else if u_index >= F::one() {
+            last
+        } else {
+            F::from(self.table.len())
+                .and_then(|n| (u_index * n).to_usize())
+                .map_or(last, |idx| cmp::min(idx, last))
+        };
+
+        match self.table[bucket] {
+            Aliased { threshold, value, alias } => {
+                if u_threshold * self.scale < threshold {
+                    value
+                } else {
+                    alias
+                }
+            }
+            Unaliased(idx) => idx,
+        }
+    }
+
+    /// Pick a random index which isn't in `excluded`, following the distribution of the
+    /// remaining indices, renormalized. Returns `None` if every index with a nonzero probability
+    /// is excluded. The table itself isn't modified, so the exclusions can change between calls.
+    ///
+    /// This first picks from the whole table and rejects excluded indices, which is fast when
+    /// the excluded indices are few and unlikely. If too many picks are rejected, it falls back to
+    /// picking from the renormalized distribution directly, which takes linear time.
+    pub fn pick_excluding<R: Rng>(&self, rng: &mut R, excluded: &[usize]) -> Option<usize> {
+        const ATTEMPTS: usize = 16;
+
+        for _ in 0..ATTEMPTS {
+            let idx = self.pick(rng);
+
+            if !excluded.contains(&idx) {
+                return Some(idx);
+            }
+        }
+
+        let mut masses = self.masses();
+
+        for &idx in excluded {
+            if let Some(p) = masses.get_mut(idx) {
+                *p = F::zero();
+            }
+        }
+
+        let total = masses.iter().fold(F::zero(), |sum, &p| sum + p);
+
+        if total <= F::zero() {
+            return None;
+        }
+
+        let mut u = F::sample_below(total, rng);
+        let last = masses.iter().rposition(|&p| p > F::zero())?;
+
+        for (idx, &p) in masses[..last].iter().enumerate() {
+            if u < p {
+                return Some(idx);
+            }
+
+            u = u - p;
+        }
+
+        Some(last)
+    }
+
+    /// Pick two distinct indices at random: the first follows the table's distribution, and the
+    /// second follows the distribution of the other indices, renormalized, as by
+    /// `pick_excluding`. Returns `None` if fewer than two indices have a nonzero probability.
+    pub fn pick_two_distinct<R: Rng>(&self, rng: &mut R) -> Option<(usize, usize)> {
+        let first = self.pick(rng);
+        self.pick_excluding(rng, &[first]).map(|second| (first, second))
+    }
+
+    /// Pick `k` distinct indices at random, without replacement: each pick follows the
+    /// distribution of the indices which haven't been picked yet, renormalized. The indices are
+    /// returned in the order they were picked. Indices with zero probability are never picked,
+    /// so fewer than `k` are returned if fewer than `k` have a nonzero probability.
+    ///
+    /// This uses the exponential keys of Efraimidis and Spirakis: every index is given the key
+    /// `ln(u) / p` for uniform `u`, and the `k` largest keys are kept. It takes `O(n + k log k)`
+    /// time on average, and one random number per index.
+    pub fn pick_distinct<R: Rng>(&self, rng: &mut R, k: usize) -> Vec<usize> {
+        let mut keys: Vec<(F, usize)> = self.masses()
+            .into_iter()
+            .enumerate()
+            .filter(|&(_, p)| p > F::zero())
+            .map(|(idx, p)| ((F::one() - F::sample_below(F::one(), rng)).ln() / p, idx))
+            .collect();
+
+        let by_key = |a: &(F, usize), b: &(F, usize)| {
+            b.0.partial_cmp(&a.0).unwrap_or(Ordering::Equal)
+        };
+
+        if k < keys.len() {
+            keys.select_nth_unstable_by(k, by_key);
+            keys.truncate(k);
+        }
+
+        keys.sort_by(by_key);
+        keys.into_iter().map(|(_, idx)| idx).collect()
+    }
+
+    /// Produce a random permutation of the indices of the table, where heavier indices tend to
+    /// come earlier: the permutation is the order in which `pick_distinct` would pick every
+    /// index. Indices with zero probability come last, in uniformly random order.
+    pub fn weighted_shuffle<R: Rng>(&self, rng: &mut R) -> Vec<usize> {
+        let mut order = self.pick_distinct(rng, self.table.len());
+        let mut picked = vec![false; self.table.len()];
+
+        for &idx in &order {
+            picked[idx] = true;
+        }
+
+        let mut rest: Vec<_> = (0..self.table.len()).filter(|&idx| !picked[idx]).collect();
+        rng.shuffle(&mut rest);
+        order.extend(rest);
+        order
+    }
+
+    /// Draw `n` indices by systematic resampling, the low-variance resampling scheme used by
+    /// particle filters: a single uniform offset `u` in `[0, 1 / n)` is drawn, and the indices
+    /// are read off the cumulative distribution at the evenly spaced points `u + k / n`. Each
+    /// index `i` then appears either `floor(n * p_i)` or `ceil(n * p_i)` times. The indices are
+    /// returned in ascending order.
+    pub fn systematic_resample<R: Rng>(&self, rng: &mut R, n: usize) -> Vec<usize> {
+        let mut picks = Vec::with_capacity(n);
+        let masses = self.masses();
+        let total = masses.iter().fold(F::zero(), |sum, &p| sum + p);
+        let nf = match F::from(n) {
+            Some(nf) if n > 0 => nf,
+            _ => return picks,
+        };
+
+        // Work in units of the total mass, so that the probes are spaced `total / n` apart.
+        let step = total / nf;
+        let mut probe = F::sample_below(step, rng);
+        let mut cumulative = F::zero();
+        let last = masses.iter().rposition(|&p| p > F::zero()).unwrap_or(0);
+
+        for (idx, &p) in masses.iter().enumerate() {
+            cumulative = cumulative + p;
+
+            while picks.len() < n && (probe < cumulative || idx == last) {
+                picks.push(idx);
+                probe = probe + step;
+            }
+        }
+
+        picks
+    }
+
+    /// Draw `n` indices by residual resampling: every index `i` is first given `floor(n * p_i)`
+    /// copies deterministically, and the remaining picks are drawn independently from a table
+    /// built from the leftover fractions `n * p_i - floor(n * p_i)`. The indices are returned in
+    /// ascending order.
+    pub fn residual_resample<R: Rng>(&self, rng: &mut R, n: usize) -> Vec<usize>
+        where F: AliasWeight
+    {
+        let mut picks = Vec::with_capacity(n);
+        let masses = self.masses();
+        let total = masses.iter().fold(F::zero(), |sum, &p| sum + p);
+        let nf = match F::from(n) {
+            Some(nf) if n > 0 => nf,
+            _ => return picks,
+        };
+
+        let mut residuals = Vec::with_capacity(masses.len());
+
+        for (idx, &p) in masses.iter().enumerate() {
+            let expected = nf * p / total;
+            let copies = expected.floor();
+
+            for _ in 0..copies.to_usize().unwrap_or(0) {
+                if picks.len() < n {
+                    picks.push(idx);
+                }
+            }
+
+            residuals.push(expected - copies);
+        }
+
+        if picks.len() < n {
+            // If rounding leaves no residual mass at all, fall back to the table itself.
+            let leftover = AliasIndexTable::from_weights(&residuals);
+            let table = leftover.as_ref().unwrap_or(self);
+
+            while picks.len() < n {
+                picks.push(table.pick(rng));
+            }
+
+            picks.sort_unstable();
+        }
+
+        picks
+    }
+
+    /// Count how many times each index is picked in `n` independent picks, without making every
+    /// pick: the counts follow a multinomial distribution. If `n` is no larger than the table,
+    /// the picks are made directly. Otherwise, the counts are drawn one index at a time from
+    /// binomial distributions conditioned on the counts so far, which takes time logarithmic in
+    /// `n` per index.
+    pub fn sample_counts<R: Rng>(&self, rng: &mut R, n: u64) -> Vec<u64> {
+        let mut counts = vec![0; self.table.len()];
+
+        if n <= self.table.len() as u64 {
+            for _ in 0..n {
+                counts[self.pick(rng)] += 1;
+            }
+
+            return counts;
+        }
+
+        let masses: Vec<f64> = self.masses()
+            .into_iter()
+            .map(|p| p.to_f64().unwrap_or(0.0))
+            .collect();
+        let last = masses.iter().rposition(|&p| p > 0.0).unwrap_or(0);
+        let mut mass = masses.iter().sum::<f64>();
+        let mut remaining = n;
+
+        for (idx, &p) in masses[..last].iter().enumerate() {
+            if remaining == 0 {
+                break;
+            } else if p > 0.0 {
+                let count = binomial(rng, remaining, (p / mass).min(1.0));
+                counts[idx] = count;
+                remaining -= count;
+                mass -= p;
+            }
+        }
+
+        // Whatever is left over goes to the last index, so that rounding error in the masses
+        // can never lose picks.
+        counts[last] += remaining;
+        counts
+    }
+
+    /// Make `n_draws` picks with `pick`, and run Pearson's chi-square goodness-of-fit test of the
+    /// counts against the probabilities recovered by `probabilities`. Indices with zero
+    /// probability don't count towards the degrees of freedom, and picking one makes the statistic
+    /// infinite. A small p-value, such as one below 0.001, suggests the picks are biased; expect
+    /// about one test in a thousand to fall below that by chance.
+    pub fn chi_square_test<R: Rng>(&self, rng: &mut R, n_draws: usize) -> ChiSquareTest {
+        let mut counts = vec![0u64; self.table.len()];
+
+        for _ in 0..n_draws {
+            counts[self.pick(rng)] += 1;
+        }
+
+        let mut statistic = 0.0;
+        let mut support = 0;
+
+        for (&count, &p) in counts.iter().zip(self.cached_probabilities()) {
+            let expected = p.to_f64().unwrap_or(0.0) * n_draws as f64;
+
+            if expected > 0.0 {
+                let diff = count as f64 - expected;
+                statistic += diff * diff / expected;
+                support += 1;
+            } else if count > 0 {
+                statistic = f64::INFINITY;
+            }
+        }
+
+        let degrees_of_freedom = support.max(1) - 1;
+        let p_value = if degrees_of_freedom == 0 {
+            if statistic > 0.0 { 0.0 } else { 1.0 }
+        } else {
+            upper_gamma_q(degrees_of_freedom as f64 / 2.0, statistic / 2.0)
+        };
+
+        ChiSquareTest {
+            statistic,
+            degrees_of_freedom,
+            p_value,
+        }
+    }
+
+    /// Recover the probability with which every index is picked, by adding up the share of each
+    /// bucket which picks it. The probabilities are computed once and cached.
+    pub fn probabilities(&self) -> Vec<F> {
+        self.cached_probabilities().to_vec()
+    }
+
+    /// The most likely index of the table, according to the probabilities recovered by
+    /// `probabilities`. Ties are broken in favor of the earliest index.
+    pub fn mode(&self) -> usize {
+        self.top_n(1)[0].0
+    }
+
+    /// The `k` most likely indices of the table with their probabilities, from the most to the
+    /// least likely, with ties broken in favor of earlier indices. All of the indices are
+    /// returned if the table has fewer than `k`.
+    pub fn top_n(&self, k: usize) -> Vec<(usize, F)> {
+        let by_prob = |a: &(usize, F), b: &(usize, F)| {
+            b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal).then(a.0.cmp(&b.0))
+        };
+
+        let mut entries: Vec<_> = self.cached_probabilities().iter().cloned().enumerate().collect();
+        let k = cmp::min(k, entries.len());
+
+        if k > 0 && k < entries.len() {
+            entries.select_nth_unstable_by(k - 1, by_prob);
+        }
+
+        entries.truncate(k);
+        entries.sort_by(by_prob);
+        entries
+    }
+
+    /// Render the buckets of the table as a Graphviz DOT graph, for visualizing how it was
+    /// constructed. Every bucket and every index is a node; each bucket has a solid edge to its
+    /// own index, labelled with the chance of keeping it, and a dashed edge to its alias, labelled
+    /// with the chance of moving to it.
+    pub fn to_dot(&self) -> String
+        where F: fmt::Display
+    {
+        self.render_dot(|idx| idx.to_string())
+    }
+
+    /// Render the buckets of the table as a text chart with one row per bucket. Each row is a bar
+    /// split between the share of the bucket which keeps its own index, drawn with `#`, and the
+    /// share which moves to its alias, drawn with `.`, followed by the indices and their shares.
+    pub fn to_ascii_chart(&self) -> String
+        where F: fmt::Display
+    {
+        self.render_ascii_chart(|idx| idx.to_string())
+    }
+
+    /// Split a bucket into its own index, its alias, and the chance of keeping its own index.
+    fn bucket_split(&self, entry: &AliasEntry<F>) -> (usize, usize, F) {
+        match *entry {
+            Aliased { threshold, value, alias } => (value, alias, threshold / self.scale),
+            Unaliased(value) => (value, value, F::one()),
+        }
+    }
+
+    fn render_dot<L>(&self, label: L) -> String
+        where F: fmt::Display,
+              L: Fn(usize) -> String
+    {
+        let mut dot = String::from("digraph alias_table {\n    rankdir=LR;\n");
+
+        for idx in 0..self.table.len() {
+            dot.push_str(&format!("    value{} [label=\"{}\", shape=ellipse];\n",
+                                  idx,
+                                  dot_escape(&label(idx))));
+        }
+
+        for (bucket, entry) in self.table.iter().enumerate() {
+            let (value, alias, p) = self.bucket_split(entry);
+
+            dot.push_str(&format!("    bucket{} [label=\"bucket {}\", shape=box];\n",
+                                  bucket,
+                                  bucket));
+            dot.push_str(&format!("    bucket{} -> value{} [label=\"{}\"];\n", bucket, value, p));
+
+            if p < F::one() {
+                dot.push_str(&format!("    bucket{} -> value{} [label=\"{}\", style=dashed];\n",
+                                      bucket,
+                                      alias,
+                                      F::one() - p));
+            }
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+
+    fn render_ascii_chart<L>(&self, label: L) -> String
+        where F: fmt::Display,
+              L: Fn(usize) -> String
+    {
+        const WIDTH: usize = 40;
+
+        let digits = self.table.len().to_string().len();
+        let mut chart = String::new();
+
+        for (bucket, entry) in self.table.iter().enumerate() {
+            let (value, alias, p) = self.bucket_split(entry);
+            let kept = (p * F::from(WIDTH).unwrap_or_else(F::zero))
+                .round()
+                .to_usize()
+                .map_or(WIDTH, |kept| cmp::min(kept, WIDTH));
+
+            chart.push_str(&format!("bucket {:>width$} |{}{}| {} {}",
+                                    bucket,
+                                    "#".repeat(kept),
+                                    ".".repeat(WIDTH - kept),
+                                    label(value),
+                                    p,
+                                    width = digits));
+
+            if p < F::one() {
+                chart.push_str(&format!(" / {} {}", label(alias), F::one() - p));
+            }
+
+            chart.push('\n');
+        }
+
+        chart
+    }
+
+    /// The cumulative distribution of the table, where `cdf[i]` is the probability of picking an
+    /// index in `0..=i`, computed from the probabilities recovered by `probabilities`. Rounding
+    /// error is not allowed to carry the CDF above one, and its last entry is exactly one, so
+    /// that inverse transform sampling against it always finds an index.
+    pub fn to_cdf(&self) -> Vec<F> {
+        let mut sum = F::zero();
+        let mut cdf: Vec<F> = self.cached_probabilities()
+            .iter()
+            .map(|&p| {
+                sum = (sum + p).min(F::one());
+                sum
+            })
+            .collect();
+
+        if let Some(last) = cdf.last_mut() {
+            *last = F::one();
+        }
+
+        cdf
+    }
+
+    /// The Shannon entropy of the distribution in nats, `-sum(p_i * ln(p_i))`, computed from the
+    /// probabilities recovered by `probabilities`. Divide by `ln(2)` for the entropy in bits.
+    pub fn entropy(&self) -> F {
+        self.cached_probabilities()
+            .iter()
+            .filter(|&&p| p > F::zero())
+            .fold(F::zero(), |h, &p| h - p * p.ln())
+    }
+
+    /// The perplexity of the distribution, `exp(entropy)`: the number of equally likely indices
+    /// which would be as unpredictable as this table. It is between one and the number of
+    /// indices with nonzero probability.
+    pub fn perplexity(&self) -> F {
+        self.entropy().exp()
+    }
+
+    /// The effective number of indices of the distribution, `1 / sum(p_i^2)`, also known as the
+    /// inverse Simpson index or, for normalized importance weights, the effective sample size.
+    /// Like `perplexity`, it is between one and the number of indices with nonzero probability,
+    /// but it discounts unlikely indices more heavily.
+    pub fn effective_support_size(&self) -> F {
+        self.hhi().recip()
+    }
+
+    /// The Gini coefficient of the probabilities of every index, including those with zero
+    /// probability: zero when every index is equally likely, approaching one as the probability
+    /// concentrates on a single index. For `n` indices the largest possible value is
+    /// `(n - 1) / n`.
+    pub fn gini(&self) -> F {
+        let mut ps = self.probabilities();
+        ps.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+
+        let n = F::from(ps.len()).unwrap_or_else(F::one);
+        let (weighted, total) = ps.iter().enumerate().fold((F::zero(), F::zero()), |acc, (i, &p)| {
+            let rank = F::from(2 * i + 1).unwrap_or_else(F::infinity) - n;
+            (acc.0 + rank * p, acc.1 + p)
+        });
+
+        weighted / (n * total)
+    }
+
+    /// The Herfindahl-Hirschman index of the probabilities of every index, `sum(p_i^2)`: the
+    /// chance that two independent picks choose the same index. It is between `1 / n` for `n`
+    /// equally likely indices and one for a table which always picks the same index.
+    pub fn hhi(&self) -> F {
+        self.cached_probabilities().iter().fold(F::zero(), |sum, &p| sum + p * p)
+    }
+
+    /// The Kullback-Leibler divergence of `other` from this table in nats,
+    /// `sum(p_i * ln(p_i / q_i))`, computed from the probabilities recovered by `probabilities`,
+    /// where `p` is this table's distribution and `q` is `other`'s. Indices beyond the end of a
+    /// table have probability zero, and the divergence is infinite if this table can pick an index
+    /// which `other` can't.
+    pub fn kl_divergence(&self, other: &AliasIndexTable<F>) -> F {
+        let qs = other.cached_probabilities();
+        self.cached_probabilities()
+            .iter()
+            .enumerate()
+            .map(|(i, &p)| (p, qs.get(i).cloned().unwrap_or_else(F::zero)))
+            .fold(F::zero(), |kl, (p, q)| kl + kl_term(p, q))
+    }
+
+    /// The total variation distance between the distributions of this table and `other`,
+    /// `sum(|p_i - q_i|) / 2`, computed from the probabilities recovered by `probabilities`. This
+    /// is the largest difference between the probabilities the two tables give any set of
+    /// indices. Indices beyond the end of a table have probability zero.
+    pub fn tv_distance(&self, other: &AliasIndexTable<F>) -> F {
+        let (ps, qs) = (self.cached_probabilities(), other.cached_probabilities());
+        let sum = (0..cmp::max(ps.len(), qs.len())).fold(F::zero(), |sum, i| {
+            let p = ps.get(i).cloned().unwrap_or_else(F::zero);
+            let q = qs.get(i).cloned().unwrap_or_else(F::zero);
+            sum + (p - q).abs()
+        });
+
+        sum / (F::one() + F::one())
+    }
+
+    /// Whether this table and `other` describe the same distribution, up to a total variation
+    /// distance of `epsilon`. This is meant for checking that rebuilding or round-tripping a table
+    /// didn't change it, where the tables differ only by rounding error.
+    pub fn approx_eq(&self, other: &AliasIndexTable<F>, epsilon: F) -> bool {
+        self.tv_distance(other) <= epsilon
+    }
+
+    /// Recover the probability with which `index` is picked, or `None` if it is out of range. If
+    /// the probabilities of every index haven't been cached yet, this scans the buckets without
+    /// allocating.
+    pub fn probability_of(&self, index: usize) -> Option<F> {
+        if index >= self.table.len() {
+            return None;
+        } else if let Some(ps) = self.probabilities.get() {
+            return Some(ps[index]);
+        }
+
+        let mass = self.table.iter().fold(F::zero(), |mass, entry| {
+            match *entry {
+                Aliased { threshold, value, alias } => {
+                    let p = threshold / self.scale;
+                    let mass = if value == index { mass + p } else { mass };
+                    if alias == index { mass + (F::one() - p) } else { mass }
+                }
+                Unaliased(idx) if idx == index => mass + F::one(),
+                Unaliased(_) => mass,
+            }
+        });
+
+        F::from(self.table.len()).map(|n| mass / n)
+    }
+
+    /// Check the structural invariants of the table: that every threshold is between zero and
+    /// the table's scale, that every bucket refers to indices within the table, that every index
+    /// is referred to by some bucket, and that the recovered probabilities sum to one, within a
+    /// tolerance of a few units of rounding error per bucket. Tables built by this crate always
+    /// pass, so a failure means a bug here rather than in the caller's weights.
+    pub fn validate(&self) -> ValidationReport {
+        let n = self.table.len();
+        let mut violations = Vec::new();
+        let mut reachable = vec![false; n];
+        let mut masses = vec![F::zero(); n];
+
+        for (bucket, entry) in self.table.iter().enumerate() {
+            let (value, alias, p) = match *entry {
+                Aliased { threshold, value, alias } => (value, alias, threshold / self.scale),
+                Unaliased(value) => (value, value, F::one()),
+            };
+
+            if !(p >= F::zero() && p <= F::one()) {
+                violations.push(Violation::ThresholdOutOfRange {
+                    bucket,
+                    threshold: p.to_f64().unwrap_or(f64::NAN),
+                });
+            }
+
+            for &(index, mass) in &[(value, p), (alias, F::one() - p)] {
+                if index >= n {
+                    violations.push(Violation::IndexOutOfRange { bucket, index });
+                } else {
+                    reachable[index] = true;
+                    masses[index] = masses[index] + mass;
+                }
+            }
+        }
+
+        let total = masses.into_iter().fold(F::zero(), |sum, mass| sum + mass);
+        let sum = F::from(n).map_or(F::nan(), |n| total / n);
+        let tolerance = rounding_tolerance(n);
+
+        if sum.is_nan() || (sum - F::one()).abs() > tolerance {
+            violations.push(Violation::NotNormalized { sum: sum.to_f64().unwrap_or(f64::NAN) });
+        }
+
+        for (index, &reachable) in reachable.iter().enumerate() {
+            if !reachable {
+                violations.push(Violation::Unreachable { index });
+            }
+        }
+
+        ValidationReport {
+            buckets: n,
+            sum: sum.to_f64().unwrap_or(f64::NAN),
+            violations,
+        }
+    }
+
+    /// The probability of every index, recovered from the buckets and cached.
+    fn cached_probabilities(&self) -> &[F] {
+        self.probabilities.get_or_init(|| {
+            let n = F::from(self.table.len()).unwrap_or_else(F::one);
+            self.masses().into_iter().map(|p| p / n).collect()
+        })
+    }
+
+    /// The probability mass of every index, recovered from the buckets of the table and scaled so
+    /// that the masses sum to the number of buckets.
+    fn masses(&self) -> Vec<F> {
+        let mut masses = vec![F::zero(); self.table.len()];
+
+        for entry in &self.table {
+            match *entry {
+                Aliased { threshold, value, alias } => {
+                    let p = threshold / self.scale;
+                    masses[value] = masses[value] + p;
+                    masses[alias] = masses[alias] + (F::one() - p);
+                }
+                Unaliased(idx) => masses[idx] = masses[idx] + F::one(),
+            }
+        }
+
+        masses
+    }
+}
+
+impl<T, F> AliasTable<T, F> {
+    /// Release any excess capacity held by the table and its values, so that their allocations
+    /// are exactly as large as they need to be.
+    pub fn shrink_to_fit(&mut self) {
+        self.indices.shrink_to_fit();
+        self.objs.shrink_to_fit();
+
+        if let Some(ref mut order) = self.order {
+            order.shrink_to_fit();
+        }
+
+        if let Some(ref mut weights) = self.weights {
+            weights.shrink_to_fit();
+        }
+    }
+
+    /// Report the heap memory allocated by the table and its values.
+    pub fn memory_usage(&self) -> MemoryUsage {
+        let order = self.order.as_ref().map_or(0, |order| order.capacity());
+        let weights = self.weights.as_ref().map_or(0, |weights| weights.capacity());
+
+        MemoryUsage {
+            table: self.indices.memory_usage() + order * mem::size_of::<usize>() +
+                   weights * mem::size_of::<F>(),
+            objs: self.objs.capacity() * mem::size_of::<T>(),
+        }
+    }
+
+    /// The number of elements in the table.
+    pub fn len(&self) -> usize {
+        self.objs.len()
+    }
+
+    /// Whether the table has no elements. Tables are never empty, so this is always `false`.
+    pub fn is_empty(&self) -> bool {
+        self.objs.is_empty()
+    }
+
+    /// Count the aliased and unaliased buckets of the table.
+    pub fn entries(&self) -> EntryCounts {
+        self.indices.entries()
+    }
+
+    /// The values of the table, in the order they are indexed by `pick_index`.
+    pub fn values(&self) -> &[T] {
+        &self.objs
+    }
+
+    /// The weights the table was built from, exactly as they were given and in the same order
+    /// as `values`, for tables built with `AliasTableBuilder::retain_weights`. Returns `None` for
+    /// any other table, whose weights can only be recovered up to rounding error with
+    /// `probabilities`.
+    pub fn weights(&self) -> Option<&[F]> {
+        self.weights.as_ref().map(|weights| &weights[..])
+    }
+
+    /// The index at which the value stored at `index` was pushed to the `AliasTableBuilder`, for
+    /// tables whose values were reordered with `AliasTableBuilder::hot_first`. For any other
+    /// table, values are stored in the order they were given, so this returns `index`.
+    pub fn original_index(&self, index: usize) -> usize {
+        self.order.as_ref().map_or(index, |order| order[index])
+    }
+
+    /// Reorder the values in order of decreasing weight, where `positions[i]` is the index at
+    /// which the `i`th value was pushed, and relabel the table's buckets to match.
+    fn reorder_hot_first(&mut self, weights: &[F], positions: Vec<usize>)
+        where F: Copy + PartialOrd
+    {
+        let mut by_weight: Vec<usize> = (0..weights.len()).collect();
+        by_weight.sort_by(|&a, &b| weights[b].partial_cmp(&weights[a]).unwrap_or(Ordering::Equal));
+
+        let mut rank = vec![0; by_weight.len()];
+        for (new, &old) in by_weight.iter().enumerate() {
+            rank[old] = new;
+        }
+
+        for entry in &mut self.indices.table {
+            match *entry {
+                Aliased { ref mut value, ref mut alias, .. } => {
+                    *value = rank[*value];
+                    *alias = rank[*alias];
+                }
+                Unaliased(ref mut value) => *value = rank[*value],
+            }
+        }
+
+        let mut objs: Vec<_> = mem::take(&mut self.objs).into_iter().enumerate().collect();
+        objs.sort_by_key(|&(old, _)| rank[old]);
+        self.objs = objs.into_iter().map(|(_, obj)| obj).collect();
+        self.weights = self.weights.take().map(|ws| by_weight.iter().map(|&old| ws[old]).collect());
+        self.order = Some(by_weight.into_iter().map(|old| positions[old]).collect());
+        self.indices.probabilities = OnceLock::new();
+    }
+}
+
+impl MemoryUsage {
+    /// The total number of bytes allocated.
+    pub fn total(&self) -> usize {
+        self.table + self.objs
+    }
+}
+
+impl<F> AliasIndexTable<F> {
+    /// Release any excess capacity held by the table, so that its allocation is exactly as large
+    /// as it needs to be. This also drops the cached probabilities, which are recovered again
+    /// the next time they're needed.
+    pub fn shrink_to_fit(&mut self) {
+        self.table.shrink_to_fit();
+        self.probabilities = OnceLock::new();
+    }
+
+    /// Report the number of bytes of heap memory allocated by the table, including its cached
+    /// probabilities, if they have been recovered.
+    pub fn memory_usage(&self) -> usize {
+        let cached = self.probabilities.get().map_or(0, |ps| ps.capacity());
+        self.table.capacity() * mem::size_of::<AliasEntry<F>>() + cached * mem::size_of::<F>()
+    }
+
+    /// The number of indices in the table.
+    pub fn len(&self) -> usize {
+        self.table.len()
+    }
+
+    /// Whether the table has no indices. Tables are never empty, so this is always `false`.
+    pub fn is_empty(&self) -> bool {
+        self.table.is_empty()
+    }
+
+    /// Count the aliased and unaliased buckets of the table.
+    pub fn entries(&self) -> EntryCounts {
+        let aliased = self.table
+            .iter()
+            .filter(|entry| matches!(**entry, Aliased { .. }))
+            .count();
+
+        EntryCounts {
+            aliased,
+            unaliased: self.table.len() - aliased,
+        }
+    }
+
+    /// Attach a value to every index of the table, producing an `AliasTable` which picks
+    /// `objs[i]` wherever this table would pick `i`. There must be exactly one value per index.
+    pub fn with_values<T>(self, objs: Vec<T>) -> Result<AliasTable<T, F>, AliasTableError> {
+        if objs.len() != self.table.len() {
+            return Err(AliasTableError::LengthMismatch {
+                values: objs.len(),
+                weights: self.table.len(),
+            });
+        }
+
+        Ok(AliasTable {
+            indices: self,
+            objs,
+            order: None,
+            weights: None,
+        })
+    }
+}
+
+impl<T, F> AliasTable<T, F>
+    where F: AliasThreshold + Zero + One
+{
+    /// Construct an alias table which picks each of the given items with equal probability.
+    pub fn uniform<I: IntoIterator<Item = T>>(items: I) -> Result<Self, AliasTableError> {
+        let objs: Vec<_> = items.into_iter().collect();
+        AliasIndexTable::uniform(objs.len())?.with_values(objs)
+    }
+}
+
+impl<F> AliasIndexTable<F>
+    where F: AliasThreshold + Zero + One
+{
+    /// Construct an index table which picks each index in `0..n` with equal probability. Every
+    /// entry is unaliased, so picking never needs to sample a threshold.
+    pub fn uniform(n: usize) -> Result<Self, AliasTableError> {
+        if n == 0 {
+            return Err(AliasTableError::Empty);
+        }
+
+        Ok(AliasIndexTable {
+            table: (0..n).map(Unaliased).collect(),
+            range: Range::new(0, n),
+            scale: F::one(),
+            probabilities: OnceLock::new(),
+        })
+    }
+}
+
+impl<T, F> AliasTable<T, F>
+    where F: AliasWeight
+{
+    /// Construct an alias table from an iterator of `(value, weight)` tuples, like `from_iter`,
+    /// but return an error instead of panicking if the weights don't describe a distribution.
+    pub fn try_from_iter<I: IntoIterator<Item = (T, F)>>(iter: I) -> Result<Self, AliasTableError> {
+        let (objs, ps): (Vec<_>, Vec<_>) = iter.into_iter().unzip();
+        AliasTable::new(objs, &ps)
+    }
+
+    /// Construct an alias table from a vector of values and a slice of their weights, where
+    /// `ps[i]` is the weight of `objs[i]`. The two must have the same length.
+    pub fn new(objs: Vec<T>, ps: &[F]) -> Result<Self, AliasTableError> {
+        if objs.len() != ps.len() {
+            return Err(AliasTableError::LengthMismatch {
+                values: objs.len(),
+                weights: ps.len(),
+            });
+        }
+
+        AliasIndexTable::from_weights(ps)?.with_values(objs)
+    }
+
+    /// Construct an alias table from an iterator of `(value, weight)` tuples with a known length,
+    /// using less memory than `try_from_iter`. The weights are written straight into the final
+    /// table, which then doubles as the worklist for partitioning and pairing, so no memory is
+    /// needed beyond the values and the table themselves.
+    pub fn from_exact_size_iter<I>(iter: I) -> Result<Self, AliasTableError>
+        where I: IntoIterator<Item = (T, F)>,
+              I::IntoIter: ExactSizeIterator
+    {
+        let iter = iter.into_iter();
+        let mut objs = Vec::with_capacity(iter.len());
+        let mut table = Vec::with_capacity(iter.len());
+        let mut psum = F::zero();
+
+        for (value, (obj, p)) in iter.enumerate() {
+            check_weight(value, p)?;
+
+            // Entries which haven't been placed yet are stored as aliasing themselves, with
+            // their weight as the threshold.
+            objs.push(obj);
+            table.push(Aliased {
+                threshold: p,
+                value,
+                alias: value,
+            });
+            psum = psum + p;
+        }
+
+        AliasIndexTable::from_pending(table, psum)?.with_values(objs)
+    }
+
+    /// Construct an alias table from a vector of values and a slice of probabilities which
+    /// already sum to one, such as the output of a softmax. Returns an error if the sum differs
+    /// from one by more than `tolerance`; otherwise, the probabilities are used as they are,
+    /// without being renormalized.
+    pub fn from_probabilities(objs: Vec<T>,
+                              ps: &[F],
+                              tolerance: F)
+                              -> Result<Self, AliasTableError> {
+        if objs.len() != ps.len() {
+            return Err(AliasTableError::LengthMismatch {
+                values: objs.len(),
+                weights: ps.len(),
+            });
+        }
+
+        AliasIndexTable::from_probabilities(ps, tolerance)?.with_values(objs)
+    }
+
+    /// Construct an alias table from an iterator of `(value, weight)` tuples with a known length
+    /// and a known total weight, such as counts taken from a counter. Since the weights don't
+    /// need to be summed first, the iterator is consumed in a single pass. The total must be the
+    /// exact sum of the weights; if it isn't, the table won't follow the weights given.
+    pub fn from_pairs_with_total<I>(iter: I, total: F) -> Result<Self, AliasTableError>
+        where I: IntoIterator<Item = (T, F)>,
+              I::IntoIter: ExactSizeIterator
+    {
+        let iter = iter.into_iter();
+
+        if iter.len() == 0 {
+            return Err(AliasTableError::Empty);
+        } else if total == F::zero() {
+            return Err(AliasTableError::ZeroTotalWeight);
+        } else if !(total > F::zero() && total.is_finite_weight()) {
+            return Err(AliasTableError::InvalidParameter { name: "total" });
+        }
+
+        let pn = F::from_u64(iter.len() as u64).ok_or(AliasTableError::CastFailure)?;
+        let pcoeff = pn / total;
+        let mut objs = Vec::with_capacity(iter.len());
+        let mut small = Vec::new();
+        let mut large = Vec::new();
+
+        for (index, (obj, p)) in iter.enumerate() {
+            check_weight(index, p)?;
+
+            let p = pcoeff * p;
+            objs.push(obj);
+
+            if p < F::one() {
+                    &mut small
+                } else {
+                    &mut large
+                }
+                .push((index, (p, F::zero())));
+        }
+
+        AliasIndexTable::from_worklists(small, large, &BuildOptions::default()).with_values(objs)
+    }
+
+    /// Rebuild the table with new weights for the same values, where `weights[i]` is the new
+    /// weight of the `i`th value. The existing table is reused, so no memory is allocated. If the
+    /// weights are invalid, an error is returned and the table is left unchanged.
+    pub fn rebuild_in_place(&mut self, weights: &[F]) -> Result<(), AliasTableError> {
+        self.indices.rebuild_in_place(weights)?;
+
+        if let Some(ref mut retained) = self.weights {
+            retained.copy_from_slice(weights);
+        }
+
+        Ok(())
+    }
+
+    /// Construct an alias table from a collection of values, using the given function to
+    /// compute the weight of each one.
+    pub fn from_fn<I, W>(items: I, weight: W) -> Result<Self, AliasTableError>
+        where I: IntoIterator<Item = T>,
+              W: FnMut(&T) -> F
+    {
+        let objs: Vec<_> = items.into_iter().collect();
+        let ps: Vec<_> = objs.iter().map(weight).collect();
+        AliasTable::new(objs, &ps)
+    }
+
+    /// Construct an alias table from an iterator of `(value, count)` tuples, such as a histogram,
+    /// where each value is picked in proportion to its count.
+    pub fn from_counts<I>(iter: I) -> Result<Self, AliasTableError>
+        where I: IntoIterator<Item = (T, u64)>
+    {
+        let (objs, counts): (Vec<_>, Vec<_>) = iter.into_iter().unzip();
+        let ps = counts.into_iter()
+            .map(F::from_u64)
+            .collect::<Option<Vec<_>>>()
+            .ok_or(AliasTableError::CastFailure)?;
+
+        AliasTable::new(objs, &ps)
+    }
+
+    /// Construct an alias table from a vector of values and their cumulative distribution, where
+    /// `cdf[i]` is the total weight of `objs[0..=i]`. The CDF need not end at one.
+    pub fn from_cdf(objs: Vec<T>, cdf: &[F]) -> Result<Self, AliasTableError> {
+        if objs.len() != cdf.len() {
+            return Err(AliasTableError::LengthMismatch {
+                values: objs.len(),
+                weights: cdf.len(),
+            });
+        }
+
+        AliasIndexTable::from_cdf(cdf)?.with_values(objs)
+    }
+
+}
+
+impl<T, F> AliasTable<T, F>
+    where F: AliasWeight + Float
+{
+    /// Change the weight of the element at `index`, rebuilding the whole table in `O(n)` time.
+    /// The weight is relative to the retained weights, for tables built with
+    /// `AliasTableBuilder::retain_weights`, and otherwise to the probabilities of the other
+    /// elements, in the same way as for `push`. If the index or weight is invalid, an error is
+    /// returned and the table is left unchanged.
+    ///
+    /// Every bucket of an alias table depends on the total weight, so there is no exact repair
+    /// which only touches the buckets of one element, and no point in deferring a rebuild: a
+    /// table which hasn't been rebuilt picks from the old weights. The buckets are reused, and so
+    /// are the retained weights if there are any, so tables built with `retain_weights` don't
+    /// allocate. Use `DynamicAliasTable` for weights which change often.
+    pub fn set_weight(&mut self, index: usize, weight: F) -> Result<(), AliasTableError> {
+        let retained = self.weights.is_some();
+        let mut weights = self.weights.take().unwrap_or_else(|| self.probabilities());
+
+        let result = match weights.get(index).cloned() {
+            Some(old) => {
+                weights[index] = weight;
+                let result = self.indices.rebuild_in_place(&weights);
+
+                if result.is_err() {
+                    weights[index] = old;
+                }
+
+                result
+            }
+            None => Err(AliasTableError::InvalidParameter { name: "index" }),
+        };
+
+        if retained {
+            self.weights = Some(weights);
+        }
+
+        result
+    }
+
+    /// Change the probability of the element at `index` to `probability` and rebuild the table
+    /// in place, as described by `AliasIndexTable::set_probability`. Retained weights, if there
+    /// are any, are updated to match, keeping their total.
+    pub fn set_probability(&mut self, index: usize, probability: F) -> Result<(), AliasTableError> {
+        let weights = reweight(&self.current_weights(), index, probability)?;
+        self.rebuild_in_place(&weights)
+    }
+
+    /// Add a value to the end of the table with the given weight, and rebuild it. The weight is
+    /// relative to the retained weights, for tables built with
+    /// `AliasTableBuilder::retain_weights`, and otherwise to the probabilities of the other
+    /// elements, which sum to one: a weight of one then gives the new value a probability of
+    /// one half. The values already in the table are kept where they are. If the weight is
+    /// invalid, an error is returned and the table is left unchanged.
+    pub fn push(&mut self, value: T, weight: F) -> Result<(), AliasTableError> {
+        let mut weights = self.current_weights();
+        weights.push(weight);
+
+        self.indices = AliasIndexTable::from_weights(&weights)?;
+        self.objs.push(value);
+
+        if let Some(ref mut order) = self.order {
+            // The value is pushed after all of those the table was built from.
+            let next = order.iter().max().map_or(0, |&max| max + 1);
+            order.push(next);
+        }
+
+        if let Some(ref mut retained) = self.weights {
+            *retained = weights;
+        }
+
+        Ok(())
+    }
+
+    /// Remove the value at `index` from the table, rebuild the table without it, and return it.
+    /// Later values move down by one index. If the index is out of range or the value is the
+    /// only one the table can pick, an error is returned and the table is left unchanged.
+    pub fn remove(&mut self, index: usize) -> Result<T, AliasTableError> {
+        if index >= self.objs.len() {
+            return Err(AliasTableError::InvalidParameter { name: "index" });
+        }
+
+        let mut weights = self.current_weights();
+        weights.remove(index);
+
+        self.indices = AliasIndexTable::from_weights(&weights)?;
+
+        if let Some(ref mut order) = self.order {
+            order.remove(index);
+        }
+
+        if let Some(ref mut retained) = self.weights {
+            *retained = weights;
+        }
+
+        Ok(self.objs.remove(index))
+    }
+
+    /// Keep only the values for which `predicate` returns `true`, like `Vec::retain`, and rebuild
+    /// the table from their weights, so that the remaining values are renormalized. The kept
+    /// values stay in the same order. If no value the table can pick would be kept, an error is
+    /// returned and the table is left unchanged.
+    pub fn retain<P>(&mut self, predicate: P) -> Result<(), AliasTableError>
+        where P: FnMut(&T) -> bool
+    {
+        let keep: Vec<bool> = self.objs.iter().map(predicate).collect();
+        let weights: Vec<F> = self.current_weights()
+            .into_iter()
+            .zip(&keep)
+            .filter(|&(_, &keep)| keep)
+            .map(|(w, _)| w)
+            .collect();
+
+        self.indices = AliasIndexTable::from_weights(&weights)?;
+
+        let mut flags = keep.iter();
+        self.objs.retain(|_| *flags.next().unwrap());
+
+        if let Some(ref mut order) = self.order {
+            let mut flags = keep.iter();
+            order.retain(|_| *flags.next().unwrap());
+        }
+
+        if let Some(ref mut retained) = self.weights {
+            *retained = weights;
+        }
+
+        Ok(())
+    }
+
+    /// The weights to rebuild the table from: the retained weights, if there are any, and the
+    /// recovered probabilities otherwise.
+    fn current_weights(&self) -> Vec<F> {
+        match self.weights {
+            Some(ref weights) => weights.clone(),
+            None => self.probabilities(),
+        }
+    }
+
+    /// Bound how far the probability with which `pick` chooses each element can be from the
+    /// probability requested by the weights the table was built from, as described by
+    /// `AliasIndexTable::probability_error_bound`. Returns `None` unless the table was built with
+    /// `AliasTableBuilder::retain_weights`.
+    pub fn probability_error_bound(&self) -> Option<Vec<F>> {
+        self.weights
+            .as_ref()
+            .and_then(|weights| self.indices.probability_error_bound(weights).ok())
+    }
+
+    /// Construct an alias table from a vector of values and their logits by applying a softmax at
+    /// the given temperature. See `AliasIndexTable::from_logits`.
+    pub fn from_logits(objs: Vec<T>,
+                       logits: &[F],
+                       temperature: F)
+                       -> Result<Self, AliasTableError> {
+        if objs.len() != logits.len() {
+            return Err(AliasTableError::LengthMismatch {
+                values: objs.len(),
+                weights: logits.len(),
+            });
+        }
+
+        AliasIndexTable::from_logits(logits, temperature)?.with_values(objs)
+    }
+
+    /// Construct an alias table following a Zipf (power-law) distribution over the given values,
+    /// where `objs[0]` is the most likely and `objs[i]` is picked with probability proportional
+    /// to `(i + 1)^-exponent`.
+    pub fn zipf_with(objs: Vec<T>, exponent: F) -> Result<Self, AliasTableError> {
+        AliasIndexTable::zipf(objs.len(), exponent)?.with_values(objs)
+    }
+
+    /// Construct an alias table from a vector of values and a slice of their natural-log
+    /// weights. See `AliasIndexTable::from_log_weights`.
+    pub fn from_log_weights(objs: Vec<T>, log_ps: &[F]) -> Result<Self, AliasTableError> {
+        if objs.len() != log_ps.len() {
+            return Err(AliasTableError::LengthMismatch {
+                values: objs.len(),
+                weights: log_ps.len(),
+            });
+        }
+
+        AliasIndexTable::from_log_weights(log_ps)?.with_values(objs)
+    }
+
+    /// Derive a table which picks only from the `k` most likely elements of this one, with
+    /// their probabilities renormalized. The derived table borrows the elements of this one, and
+    /// keeps them in the same order. Ties between equally likely elements are broken in favor of
+    /// the earlier one.
+    pub fn top_k(&self, k: usize) -> Result<AliasTable<&T, F>, AliasTableError> {
+        let masses = self.indices.masses();
+        let mut kept: Vec<usize> = (0..masses.len()).collect();
+        kept.sort_by(|&a, &b| masses[b].partial_cmp(&masses[a]).unwrap_or(Ordering::Equal));
+        kept.truncate(k);
+        kept.sort_unstable();
+
+        self.derive(kept.into_iter().map(|idx| (idx, masses[idx])))
+    }
+
+    /// Derive a table which picks only from the nucleus of this one: the smallest set of its most
+    /// likely elements whose probabilities sum to at least `p`, renormalized. `p` must be in
+    /// `(0, 1]`. The derived table borrows the elements of this one, and keeps them in the same
+    /// order.
+    pub fn top_p(&self, p: F) -> Result<AliasTable<&T, F>, AliasTableError> {
+        if !(p > F::zero() && p <= F::one()) {
+            return Err(AliasTableError::InvalidParameter { name: "p" });
+        }
+
+        let masses = self.indices.masses();
+        let total = masses.iter().fold(F::zero(), |sum, &p| sum + p);
+        let mut order: Vec<usize> = (0..masses.len()).collect();
+        order.sort_by(|&a, &b| masses[b].partial_cmp(&masses[a]).unwrap_or(Ordering::Equal));
+
+        let mut cumulative = F::zero();
+        let mut kept = Vec::new();
+
+        for idx in order {
+            if cumulative >= p * total || masses[idx] == F::zero() {
+                break;
+            }
+
+            cumulative = cumulative + masses[idx];
+            kept.push(idx);
+        }
+
+        kept.sort_unstable();
+        self.derive(kept.into_iter().map(|idx| (idx, masses[idx])))
+    }
+
+    /// Derive a table which picks from the same elements as this one, with every probability
+    /// `p_i` replaced by `p_i^(1 / temperature)` and renormalized. Temperatures below one sharpen
+    /// the distribution towards its most likely elements, and temperatures above one flatten it
+    /// towards uniform. The temperature must be positive and finite. The derived table borrows
+    /// the elements of this one, and keeps them in the same order.
+    pub fn with_temperature(&self, temperature: F) -> Result<AliasTable<&T, F>, AliasTableError> {
+        if !(temperature > F::zero() && temperature.is_finite()) {
+            return Err(AliasTableError::InvalidParameter { name: "temperature" });
+        }
+
+        let log_ps: Vec<_> = self.indices
+            .masses()
+            .into_iter()
+            .map(|p| p.ln() / temperature)
+            .collect();
+
+        AliasIndexTable::from_log_weights(&log_ps)?.with_values(self.objs.iter().collect())
+    }
+
+    /// Derive a table which picks only from the elements of this one which match `predicate`,
+    /// with their probabilities renormalized. Returns `AliasTableError::Empty` if no elements
+    /// match, or `AliasTableError::ZeroTotalWeight` if only elements which are never picked do.
+    /// The derived table borrows the elements of this one, and keeps them in the same order.
+    pub fn filter<P>(&self, mut predicate: P) -> Result<AliasTable<&T, F>, AliasTableError>
+        where P: FnMut(&T) -> bool
+    {
+        let masses = self.indices.masses();
+        let kept = (0..self.objs.len()).filter(|&idx| predicate(&self.objs[idx]));
+
+        self.derive(kept.map(|idx| (idx, masses[idx])))
+    }
+
+    /// Combine two tables into one which picks from `a` with total probability
+    /// `mass_a / (mass_a + mass_b)` and from `b` with the rest, keeping the relative
+    /// probabilities within each table. The values of `a` come first, followed by those of `b`.
+    /// The masses must be finite and non-negative, and not both zero.
+    pub fn merge(a: AliasTable<T, F>,
+                 b: AliasTable<T, F>,
+                 mass_a: F,
+                 mass_b: F)
+                 -> Result<AliasTable<T, F>, AliasTableError> {
+        if !(mass_a >= F::zero() && mass_a.is_finite()) {
+            return Err(AliasTableError::InvalidParameter { name: "mass_a" });
+        } else if !(mass_b >= F::zero() && mass_b.is_finite()) {
+            return Err(AliasTableError::InvalidParameter { name: "mass_b" });
+        }
+
+        let total = mass_a + mass_b;
+
+        if total == F::zero() {
+            return Err(AliasTableError::ZeroTotalWeight);
+        }
+
+        let (share_a, share_b) = (mass_a / total, mass_b / total);
+        let ps: Vec<F> = a.indices
+            .cached_probabilities()
+            .iter()
+            .map(|&p| p * share_a)
+            .chain(b.indices.cached_probabilities().iter().map(|&p| p * share_b))
+            .collect();
+
+        let mut objs = a.objs;
+        objs.extend(b.objs);
+
+        AliasTable::new(objs, &ps)
+    }
+
+    /// Build the exact distribution of `x + y`, where `x` is picked from this table and `y` is
+    /// picked independently from `other`. The new table has an entry for every pair of values
+    /// with nonzero probability, whose weight is the product of their probabilities, ordered by
+    /// this table's value first. Equal sums are kept as separate entries; use `convolve_dedup`
+    /// to merge them.
+    pub fn convolve(&self, other: &AliasTable<T, F>) -> Result<AliasTable<T, F>, AliasTableError>
+        where T: Add<Output = T> + Clone
+    {
+        AliasTable::try_from_iter(self.sum_pairs(other))
+    }
+
+    /// Build the exact distribution of `x + y` like `convolve`, but merge equal sums into a
+    /// single entry, stored in the order of their first occurrence. Convolving a table with
+    /// itself repeatedly this way gives the distribution of the sum of several picks, such as the
+    /// total of several dice, without the table growing exponentially.
+    pub fn convolve_dedup(&self,
+                          other: &AliasTable<T, F>)
+                          -> Result<AliasTable<T, F>, AliasTableError>
+        where T: Add<Output = T> + Clone + Eq + Hash
+    {
+        AliasTable::from_iter_dedup(self.sum_pairs(other))
+    }
+
+    /// Every sum of a value of this table and a value of `other` with nonzero probability, with
+    /// the probability of the pair.
+    fn sum_pairs<'a>(&'a self,
+                     other: &'a AliasTable<T, F>)
+                     -> impl Iterator<Item = (T, F)> + 'a
+        where T: Add<Output = T> + Clone
+    {
+        self.support().flat_map(move |(x, p)| {
+            other.support().map(move |(y, q)| (x.clone() + y.clone(), p * q))
+        })
+    }
+
+    /// Build a table over references to some of the elements of this one, given as pairs of
+    /// their indices and new weights.
+    fn derive<I>(&self, entries: I) -> Result<AliasTable<&T, F>, AliasTableError>
+        where I: IntoIterator<Item = (usize, F)>
+    {
+        let (objs, ps): (Vec<_>, Vec<_>) = entries.into_iter()
+            .map(|(idx, p)| (&self.objs[idx], p))
+            .unzip();
+
+        AliasTable::new(objs, &ps)
+    }
+}
+
+impl<F> AliasIndexTable<F>
+    where F: AliasWeight
+{
+    /// Construct an index table from a slice of weights, where `ps[i]` is the weight of index `i`.
+    pub fn from_weights(ps: &[F]) -> Result<Self, AliasTableError> {
+        AliasIndexTable::from_weights_with(ps, &BuildOptions::default())
+    }
+
+    /// Construct an index table from a slice of probabilities which already sum to one, where
+    /// `ps[i]` is the probability of index `i`. Returns an error if the sum differs from one by
+    /// more than `tolerance`; otherwise, the probabilities are used as they are, without being
+    /// renormalized.
+    pub fn from_probabilities(ps: &[F], tolerance: F) -> Result<Self, AliasTableError> {
+        if ps.is_empty() {
+            return Err(AliasTableError::Empty);
+        } else if !(tolerance >= F::zero() && tolerance.is_finite_weight()) {
+            return Err(AliasTableError::InvalidParameter { name: "tolerance" });
+        }
+
+        let mut psum = F::zero();
+
+        for (index, &p) in ps.iter().enumerate() {
+            check_weight(index, p)?;
+            psum = psum + p;
+        }
+
+        let error = if psum < F::one() { F::one() - psum } else { psum - F::one() };
+
+        if error > tolerance {
+            return Err(AliasTableError::NotNormalized { sum: psum.weight_to_f64() });
+        } else if psum == F::zero() {
+            return Err(AliasTableError::ZeroTotalWeight);
+        }
+
+        let pn = F::from_u64(ps.len() as u64).ok_or(AliasTableError::CastFailure)?;
+
+        let (small, large): (Vec<_>, Vec<_>) = ps.iter()
+            .map(|&p| (pn * p, F::zero()))
+            .enumerate()
+            .partition(|&(_, (p, _))| p < F::one());
+
+        Ok(AliasIndexTable::from_worklists(small, large, &BuildOptions::default()))
+    }
+
+    /// Rebuild the index table with new weights, where `ps[i]` is the new weight of index `i`. The
+    /// existing table is reused, so no memory is allocated. If the weights are invalid, an error
+    /// is returned and the table is left unchanged.
+    pub fn rebuild_in_place(&mut self, ps: &[F]) -> Result<(), AliasTableError> {
+        if ps.len() != self.table.len() {
+            return Err(AliasTableError::LengthMismatch {
+                values: self.table.len(),
+                weights: ps.len(),
+            });
+        }
+
+        let mut psum = F::zero();
+
+        for (index, &p) in ps.iter().enumerate() {
+            check_weight(index, p)?;
+            psum = psum + p;
+        }
+
+        if psum == F::zero() {
+            return Err(AliasTableError::ZeroTotalWeight);
+        } else if !psum.is_finite_weight() {
+            return Err(AliasTableError::WeightOverflow);
+        }
+
+        let mut table = mem::take(&mut self.table);
+        table.clear();
+        table.extend(ps.iter().enumerate().map(|(value, &p)| {
+            Aliased {
+                threshold: p,
+                value,
+                alias: value,
+            }
+        }));
+
+        *self = AliasIndexTable::from_pending(table, psum)?;
+        Ok(())
+    }
+
+    fn from_weights_with(ps: &[F], options: &BuildOptions) -> Result<Self, AliasTableError> {
+        if ps.is_empty() {
+            return Err(AliasTableError::Empty);
+        }
+
+        for (index, &p) in ps.iter().enumerate() {
+            check_weight(index, p)?;
+        }
+
+        let psum: F = if options.compensated && F::is_signed_weight() {
+            let (sum, c) = ps.iter()
+                .fold((F::zero(), F::zero()), |(sum, c), &p| neumaier_add(sum, c, p));
+            sum + c
+        } else {
+            ps.iter().fold(F::zero(), |sum, &p| sum + p)
+        };
+
+        if psum == F::zero() {
+            return Err(AliasTableError::ZeroTotalWeight);
+        } else if !psum.is_finite_weight() {
+            return Err(AliasTableError::WeightOverflow);
+        }
+
+        if options.zero_weights == ZeroWeights::Reject {
+            if let Some(index) = ps.iter().position(|&p| p == F::zero()) {
+                return Err(AliasTableError::ZeroWeight { index });
+            }
+        }
+
+        let pn = F::from_u64(ps.len() as u64).ok_or(AliasTableError::CastFailure)?;
+        let pcoeff = pn / psum;
+
+        // Worklist entries carry a compensation term alongside their scaled weight, which stays
+        // zero unless compensated summation is enabled.
+        let (small, large): (Vec<_>, Vec<_>) = ps.iter()
+            .map(|&p| (pcoeff * p, F::zero()))
+            .enumerate()
+            .partition(|&(_, (p, _))| p < F::one());
+
+        Ok(AliasIndexTable::from_worklists(small, large, options))
+    }
+
+    /// Turn a table of pending entries, as built by `AliasTable::from_exact_size_iter`, into an
+    /// index table in place.
+    fn from_pending(mut table: Vec<AliasEntry<F>>, psum: F) -> Result<Self, AliasTableError> {
+        if table.is_empty() {
+            return Err(AliasTableError::Empty);
+        } else if psum == F::zero() {
+            return Err(AliasTableError::ZeroTotalWeight);
+        } else if !psum.is_finite_weight() {
+            return Err(AliasTableError::WeightOverflow);
+        }
+
+        let pn = F::from_u64(table.len() as u64).ok_or(AliasTableError::CastFailure)?;
+        let pcoeff = pn / psum;
+
+        fn pending<F: Copy>(entry: &AliasEntry<F>) -> (usize, F) {
+            match *entry {
+                Aliased { threshold, value, .. } => (value, threshold),
+                Unaliased(_) => unreachable!("pending alias table entry was already placed"),
+            }
+        }
+
+        for entry in &mut table {
+            if let Aliased { ref mut threshold, .. } = *entry {
+                *threshold = pcoeff * *threshold;
+            }
+        }
+
+        // Partition the small entries to the front, and the large entries to the back.
+        let (mut lo, mut hi) = (0, table.len());
+
+        while lo < hi {
+            if pending(&table[lo]).1 < F::one() {
+                lo += 1;
+            } else {
+                hi -= 1;
+                table.swap(lo, hi);
+            }
+        }
+
+        let fallback = if lo < table.len() {
+            pending(&table[lo]).0
+        } else {
+            let mass = |k: &usize| pending(&table[*k]).1;
+            let best = (0..table.len())
+                .max_by(|a, b| mass(a).partial_cmp(&mass(b)).unwrap_or(Ordering::Equal))
+                .unwrap();
+            pending(&table[best]).0
+        };
+
+        // Every position before `j` holds a small entry until it is placed, and `j` is the
+        // current large entry; when it becomes small, it is placed once `i` catches up with it.
+        let (mut i, mut j) = (0, lo);
+
+        while i < j && j < table.len() {
+            let (l, p_l) = pending(&table[i]);
+            let (g, p_g) = pending(&table[j]);
+
+            table[i] = Aliased {
+                threshold: p_l,
+                value: l,
+                alias: g,
+            };
+
+            let p_g = (p_g + p_l) - F::one();
+
+            table[j] = Aliased {
+                threshold: p_g,
+                value: g,
+                alias: g,
+            };
+
+            if p_g < F::one() {
+                j += 1;
+            }
+
+            i += 1;
+        }
+
+        for entry in &mut table[i..] {
+            let (idx, p) = pending(entry);
+            *entry = leftover(idx, p, Some(fallback));
+        }
+
+        Ok(AliasIndexTable {
+            range: Range::new(0, table.len()),
+            scale: F::one(),
+            probabilities: OnceLock::new(),
+            table,
+        })
+    }
+
+    /// Pair off the entries of the small and large worklists into a table. Consumes the scaled
+    /// weights produced by normalizing to a mean of one.
+    fn from_worklists(small: Vec<Pending<F>>,
+                      large: Vec<Pending<F>>,
+                      options: &BuildOptions)
+                      -> Self {
+        let mut table = Vec::with_capacity(small.len() + large.len());
+        let fallback = large.first()
+            .or_else(|| small.iter().max_by(|a, b| cmp_mass(a, b)))
+            .map(|e| e.0);
+        let mut small = Worklist::new(small, options.canonical);
+        let mut large = Worklist::new(large, options.canonical);
+
+
+        while !(small.is_empty() || large.is_empty()) {
+            let (l, (p_l, c_l)) = small.pop().unwrap();
+            let (g, (p_g, c_g)) = large.pop().unwrap();
+            let p_l = p_l + c_l;
+
+            table.push(Aliased {
+                threshold: p_l,
+                value: l,
+                alias: g,
+            });
+
+            let (p_g, c_g) = if options.compensated && F::is_signed_weight() {
+                let (p_g, c_g) = neumaier_add(p_g, c_g, p_l);
+                neumaier_sub(p_g, c_g, F::one())
+            } else {
+                ((p_g + p_l) - F::one(), F::zero())
+            };
+
+            if p_g + c_g < F::one() {
+                    &mut small
+                } else {
+                    &mut large
+                }
+                .push((g, (p_g, c_g)));
+        }
+
+        table.extend(large.into_vec().into_iter().map(|(g, _)| Unaliased(g)));
+
+        table.extend(small.into_vec()
+            .into_iter()
+            .map(|(l, (p_l, c_l))| leftover(l, p_l + c_l, fallback)));
+
+        AliasIndexTable {
+            range: Range::new(0, table.len()),
+            scale: F::one(),
+            probabilities: OnceLock::new(),
+            table,
+        }
+    }
+
+    /// Construct an index table from a cumulative distribution, where `cdf[i]` is the total
+    /// weight of the indices `0..=i`. Returns an error if the CDF is negative or decreasing.
+    pub fn from_cdf(cdf: &[F]) -> Result<Self, AliasTableError> {
+        if let Some(index) = cdf.iter().position(|p| !p.is_finite_weight()) {
+            return Err(AliasTableError::NonFiniteWeight {
+                index,
+                value: cdf[index].weight_to_f64(),
+            });
+        }
+
+        let mut prev = F::zero();
+        let mut ps = Vec::with_capacity(cdf.len());
+
+        for (index, &c) in cdf.iter().enumerate() {
+            if c < prev {
+                return Err(AliasTableError::NonMonotoneCdf { index });
+            }
+
+            ps.push(c - prev);
+            prev = c;
+        }
+
+        AliasIndexTable::from_weights(&ps)
+    }
+
+}
+
+impl<F> AliasIndexTable<F>
+    where F: AliasWeight + Float
+{
+    /// Construct an index table from a slice of logits by applying a softmax at the given
+    /// temperature, so that index `i` is picked with probability proportional to
+    /// `exp(logits[i] / temperature)`. The temperature must be positive and finite.
+    pub fn from_logits(logits: &[F], temperature: F) -> Result<Self, AliasTableError> {
+        if !(temperature > F::zero() && temperature.is_finite()) {
+            return Err(AliasTableError::InvalidParameter { name: "temperature" });
+        }
+
+        let log_ps: Vec<_> = logits.iter().map(|&l| l / temperature).collect();
+        AliasIndexTable::from_log_weights(&log_ps)
+    }
+
+    /// Construct an index table following a Zipf (power-law) distribution, where index `i` has
+    /// rank `i + 1` and is picked with probability proportional to `(i + 1)^-exponent`.
+    pub fn zipf(n: usize, exponent: F) -> Result<Self, AliasTableError> {
+        if !exponent.is_finite() {
+            return Err(AliasTableError::InvalidParameter { name: "exponent" });
+        }
+
+        let log_ps = (1..n + 1)
+            .map(|k| F::from(k).map(|k| -exponent * k.ln()))
+            .collect::<Option<Vec<_>>>()
+            .ok_or(AliasTableError::CastFailure)?;
+
+        AliasIndexTable::from_log_weights(&log_ps)
+    }
+
+    /// Construct an index table following a Poisson distribution with mean `lambda`, truncated
+    /// to the outcomes `0..=max_k` and renormalized.
+    pub fn poisson(lambda: F, max_k: usize) -> Result<Self, AliasTableError> {
+        if !(lambda > F::zero() && lambda.is_finite()) {
+            return Err(AliasTableError::InvalidParameter { name: "lambda" });
+        }
+
+        // ln(lambda^k / k!), dropping the constant factor of e^-lambda.
+        let ln_lambda = lambda.ln();
+        let mut log_p = F::zero();
+        let mut log_ps = Vec::with_capacity(max_k + 1);
+        log_ps.push(log_p);
+
+        for k in 1..max_k + 1 {
+            let k = F::from(k).ok_or(AliasTableError::CastFailure)?;
+            log_p = log_p + ln_lambda - k.ln();
+            log_ps.push(log_p);
+        }
+
+        AliasIndexTable::from_log_weights(&log_ps)
+    }
+
+    /// Construct an index table following a binomial distribution with `n` trials and success
+    /// probability `p`, over the outcomes `0..=n`.
+    pub fn binomial(n: usize, p: F) -> Result<Self, AliasTableError> {
+        if !(p >= F::zero() && p <= F::one()) {
+            return Err(AliasTableError::InvalidParameter { name: "p" });
+        }
+
+        let nf = F::from(n).ok_or(AliasTableError::CastFailure)?;
+        let (ln_p, ln_q) = (p.ln(), (F::one() - p).ln());
+
+        // ln(n choose k), built up incrementally from ln(n choose 0) = 0.
+        let mut log_c = F::zero();
+        let mut log_ps = Vec::with_capacity(n + 1);
+        log_ps.push(xlny(nf, ln_q));
+
+        for k in 1..n + 1 {
+            let kf = F::from(k).ok_or(AliasTableError::CastFailure)?;
+            log_c = log_c + (nf - kf + F::one()).ln() - kf.ln();
+            log_ps.push(log_c + xlny(kf, ln_p) + xlny(nf - kf, ln_q));
+        }
+
+        AliasIndexTable::from_log_weights(&log_ps)
+    }
+
+    /// Construct an index table following a geometric distribution counting the failures before
+    /// the first success, with success probability `p`, truncated to the outcomes `0..=max_k` and
+    /// renormalized.
+    pub fn geometric(p: F, max_k: usize) -> Result<Self, AliasTableError> {
+        if !(p > F::zero() && p <= F::one()) {
+            return Err(AliasTableError::InvalidParameter { name: "p" });
+        }
+
+        let ln_q = (F::one() - p).ln();
+        let log_ps = (0..max_k + 1)
+            .map(|k| F::from(k).map(|k| xlny(k, ln_q)))
+            .collect::<Option<Vec<_>>>()
+            .ok_or(AliasTableError::CastFailure)?;
+
+        AliasIndexTable::from_log_weights(&log_ps)
+    }
+
+    /// Construct an index table from a slice of natural-log weights, where `log_ps[i]` is the
+    /// log-weight of index `i`. The weights are shifted by their maximum before exponentiating
+    /// (the log-sum-exp trick), so log-probabilities far outside the range of `F` are handled
+    /// without overflow or underflow. A log-weight of negative infinity is a weight of zero.
+    pub fn from_log_weights(log_ps: &[F]) -> Result<Self, AliasTableError> {
+        if log_ps.is_empty() {
+            return Err(AliasTableError::Empty);
+        }
+
+        if let Some(index) = log_ps.iter().position(|&p| p.is_nan() || p == F::infinity()) {
+            return Err(AliasTableError::NonFiniteWeight {
+                index,
+                value: log_ps[index].weight_to_f64(),
+            });
+        }
+
+        let max = log_ps.iter().cloned().fold(F::neg_infinity(), F::max);
+
+        if max == F::neg_infinity() {
+            return Err(AliasTableError::ZeroTotalWeight);
+        }
+
+        let ps: Vec<_> = log_ps.iter().map(|&p| (p - max).exp()).collect();
+        AliasIndexTable::from_weights(&ps)
+    }
+
+    /// Change the probability of `index` to `probability` and rebuild the table in place. The
+    /// other indices keep their probabilities relative to each other, scaled by
+    /// `(1 - probability) / (1 - p)` where `p` is the old probability of `index`, so calling
+    /// this repeatedly with the same arguments leaves the table as it was after the first call.
+    /// Returns an error, leaving the table unchanged, if the index is out of range, if the
+    /// probability is not between zero and one, or if it is below one for an index which is the
+    /// only one the table can pick, since there is then no probability to give the others.
+    ///
+    /// This allocates the new probabilities, and, as for `AliasTable::set_weight`, takes time
+    /// linear in the size of the table.
+    pub fn set_probability(&mut self, index: usize, probability: F) -> Result<(), AliasTableError> {
+        let weights = reweight(self.cached_probabilities(), index, probability)?;
+        self.rebuild_in_place(&weights)
+    }
+
+    /// Bound how far the probability with which `pick` chooses each index can be from the
+    /// probability requested by `weights`, `weights[i] / sum(weights)`, as a result of rounding
+    /// error while building the table. The bound is computed after the fact from the stored
+    /// thresholds, so it holds no matter how the table was built, and accounts for the rounding
+    /// error of the bound's own arithmetic and for the resolution of the uniform samples which
+    /// thresholds are compared against. It doesn't apply to `pick_fast` or `pick_with`, which
+    /// draw their samples differently. Returns an error if the weights are invalid or there
+    /// aren't exactly as many as indices.
+    pub fn probability_error_bound(&self, weights: &[F]) -> Result<Vec<F>, AliasTableError> {
+        let n = self.table.len();
+
+        if weights.len() != n {
+            return Err(AliasTableError::LengthMismatch {
+                values: n,
+                weights: weights.len(),
+            });
+        }
+
+        let mut total = F::zero();
+
+        for (index, &w) in weights.iter().enumerate() {
+            check_weight(index, w)?;
+            total = total + w;
+        }
+
+        if total == F::zero() {
+            return Err(AliasTableError::ZeroTotalWeight);
+        } else if !total.is_finite() {
+            return Err(AliasTableError::WeightOverflow);
+        }
+
+        // The number of buckets each index can be picked from.
+        let mut refs = vec![0usize; n];
+
+        for entry in &self.table {
+            let (value, alias, _) = self.bucket_split(entry);
+            refs[value] += 1;
+
+            if alias != value {
+                refs[alias] += 1;
+            }
+        }
+
+        // Recovering a probability takes at most two roundings per bucket and one for the
+        // division by `n`, and a requested probability takes `n - 1` for the sum and one for the
+        // division, where each rounding has a relative error of at most `u`.
+        let u = F::epsilon() / (F::one() + F::one());
+        let gamma = |k: usize| {
+            let ku = F::from(k).unwrap_or_else(F::infinity) * u;
+            ku / (F::one() - ku)
+        };
+        let (recovered_rel, requested_rel) = (gamma(2 * n + 2), gamma(n));
+        let buckets = F::from(n).unwrap_or_else(F::infinity);
+
+        let bounds = self.cached_probabilities()
+            .iter()
+            .zip(weights)
+            .zip(&refs)
+            .map(|((&p, &w), &refs)| {
+                let r = w / total;
+                let rounding = p * recovered_rel + r * requested_rel;
+
+                // Uniform samples below one are drawn from the mantissa bits of the float type,
+                // 52 for `f64` and 23 for `f32`, so they are multiples of `F::epsilon()`, which
+                // moves the chance of passing a threshold by at most `F::epsilon()`.
+                let resolution = F::from(refs).unwrap_or_else(F::infinity) * F::epsilon() /
+                                 buckets;
+
+                ((p - r).abs() + rounding + resolution) * (F::one() + F::from(8).unwrap() * u)
+            })
+            .collect();
+
+        Ok(bounds)
+    }
+}
+
+impl<T, F> AliasTable<T, F>
+    where T: Eq + Hash,
+          F: AliasWeight
+{
+    /// Construct an alias table from an iterator of `(value, weight)` tuples like
+    /// `try_from_iter`, but merge repeated values into a single entry whose weight is the sum of
+    /// their weights. Values are stored in the order of their first occurrence.
+    pub fn from_iter_dedup<I>(iter: I) -> Result<Self, AliasTableError>
+        where I: IntoIterator<Item = (T, F)>
+    {
+        AliasTable::try_from_iter(merge_duplicates(iter))
+    }
+
+    /// Construct the empirical distribution of a stream of samples, where each distinct value is
+    /// picked in proportion to the number of times it occurs. Values are stored in the order of
+    /// their first occurrence.
+    pub fn from_samples<I: IntoIterator<Item = T>>(samples: I) -> Result<Self, AliasTableError> {
+        AliasTable::from_counts(merge_duplicates(samples.into_iter().map(|value| (value, 1u64))))
+    }
+}
+
+/// Merge the weights of repeated values by summing them, keeping the values in the order of
+/// their first occurrence.
+fn merge_duplicates<T, W, I>(iter: I) -> Vec<(T, W)>
+    where T: Eq + Hash,
+          W: Zero,
+          I: IntoIterator<Item = (T, W)>
+{
+    let mut merged = HashMap::new();
+
+    for (value, weight) in iter {
+        let next = merged.len();
+        let entry = merged.entry(value).or_insert((next, W::zero()));
+        entry.1 = mem::replace(&mut entry.1, W::zero()) + weight;
+    }
+
+    let mut merged: Vec<_> = merged.into_iter().collect();
+    merged.sort_by_key(|&(_, (first, _))| first);
+    merged.into_iter().map(|(value, (_, weight))| (value, weight)).collect()
+}
+
+impl<F> AliasTable<usize, F>
+    where F: AliasWeight + Float
+{
+    /// Construct an alias table over the ranks `1..=n` following a Zipf (power-law)
+    /// distribution, where rank `k` is picked with probability proportional to `k^-exponent`.
+    pub fn zipf(n: usize, exponent: F) -> Result<Self, AliasTableError> {
+        AliasTable::zipf_with((1..n + 1).collect(), exponent)
+    }
+
+    /// Construct an alias table over `0..=max_k` following a truncated Poisson distribution. See
+    /// `AliasIndexTable::poisson`.
+    pub fn poisson(lambda: F, max_k: usize) -> Result<Self, AliasTableError> {
+        AliasIndexTable::poisson(lambda, max_k)?.with_values((0..max_k + 1).collect())
+    }
+
+    /// Construct an alias table over `0..=n` following a binomial distribution. See
+    /// `AliasIndexTable::binomial`.
+    pub fn binomial(n: usize, p: F) -> Result<Self, AliasTableError> {
+        AliasIndexTable::binomial(n, p)?.with_values((0..n + 1).collect())
+    }
+
+    /// Construct an alias table over `0..=max_k` following a truncated geometric distribution.
+    /// See `AliasIndexTable::geometric`.
+    pub fn geometric(p: F, max_k: usize) -> Result<Self, AliasTableError> {
+        AliasIndexTable::geometric(p, max_k)?.with_values((0..max_k + 1).collect())
+    }
+}
+
+impl<T> AliasTable<T, u64> {
+    /// Construct an alias table from integer weights, where `weights[i]` is the weight of
+    /// `objs[i]`. Construction is done in exact integer arithmetic and the thresholds are stored
+    /// as integers out of the total weight, so the sampled distribution matches the weights
+    /// exactly. Weights can be given as any unsigned integer type up to `u128`, but the
+    /// thresholds are stored as `u64`, so the weights must sum to at most `u64::MAX`, and a
+    /// `WeightOverflow` error is returned if they don't.
+    pub fn from_integer_weights<W>(objs: Vec<T>, weights: &[W]) -> Result<Self, AliasTableError>
+        where W: Copy + Into<u128>
+    {
+        if objs.len() != weights.len() {
+            return Err(AliasTableError::LengthMismatch {
+                values: objs.len(),
+                weights: weights.len(),
+            });
+        }
+
+        AliasIndexTable::from_integer_weights(weights)?.with_values(objs)
+    }
+}
+
+impl AliasIndexTable<u64> {
+    /// Construct an index table from integer weights, where `weights[i]` is the weight of index
+    /// `i`. See `AliasTable::from_integer_weights`.
+    pub fn from_integer_weights<W>(weights: &[W]) -> Result<Self, AliasTableError>
+        where W: Copy + Into<u128>
+    {
+        if weights.is_empty() {
+            return Err(AliasTableError::Empty);
+        }
+
+        let total = weights.iter()
+            .try_fold(0u128, |sum, &w| sum.checked_add(w.into()))
+            .filter(|&total| total <= u64::MAX as u128)
+            .ok_or(AliasTableError::WeightOverflow)? as u64;
+
+        if total == 0 {
+            return Err(AliasTableError::ZeroTotalWeight);
+        }
+
+        // Every bucket holds `total` units of mass, and each weight is scaled by the number of
+        // buckets, so that the scaled weights sum to exactly `n * total`. Products can exceed
+        // `u64`, but every threshold stored in the table is strictly less than `total`.
+        let pn = weights.len() as u128;
+        let cap = total as u128;
+
+        let (mut small, mut large): (Vec<_>, Vec<_>) = weights.iter()
+            .map(|&w| pn * w.into())
+            .enumerate()
+            .partition(|&(_, p)| p < cap);
+        let mut table = Vec::with_capacity(weights.len());
+
+        while let Some((l, p_l)) = small.pop() {
+            // With exact arithmetic the small worklist can only run out first.
+            let (g, p_g) = large.pop().expect("exact alias construction lost mass");
+
+            table.push(Aliased {
+                threshold: p_l as u64,
+                value: l,
+                alias: g,
+            });
+
+            let p_g = (p_g + p_l) - cap;
+
+            if p_g < cap {
+                    &mut small
+                } else {
+                    &mut large
+                }
+                .push((g, p_g));
+        }
+
+        table.extend(large.iter().map(|&(g, _)| Unaliased(g)));
+
+        Ok(AliasIndexTable {
+            range: Range::new(0, table.len()),
+            scale: total,
+            probabilities: OnceLock::new(),
+            table,
+        })
+    }
+}
+
+#[cfg(feature = "num-rational")]
+impl<T> AliasTable<T, u64> {
+    /// Construct an alias table from exact rational weights, where `weights[i]` is the weight of
+    /// `objs[i]`. The weights are brought to a common denominator and the table is built exactly
+    /// as with `from_integer_weights`, so no rounding happens during construction or picking.
+    /// Only available with the `num-rational` feature.
+    pub fn from_ratio_weights(objs: Vec<T>,
+                              weights: &[Ratio<u64>])
+                              -> Result<Self, AliasTableError> {
+        if objs.len() != weights.len() {
+            return Err(AliasTableError::LengthMismatch {
+                values: objs.len(),
+                weights: weights.len(),
+            });
+        }
+
+        AliasIndexTable::from_ratio_weights(weights)?.with_values(objs)
+    }
+
+    /// Compute the exact probability with which each value is picked. See
+    /// `AliasIndexTable::exact_probabilities`.
+    pub fn exact_probabilities(&self) -> Vec<Ratio<u128>> {
+        self.indices.exact_probabilities()
+    }
+}
+
+#[cfg(feature = "num-rational")]
+impl AliasIndexTable<u64> {
+    /// Construct an index table from exact rational weights, where `weights[i]` is the weight of
+    /// index `i`. See `AliasTable::from_ratio_weights`.
+    pub fn from_ratio_weights(weights: &[Ratio<u64>]) -> Result<Self, AliasTableError> {
+        let denom = weights.iter().try_fold(1u64, |l, w| {
+            (l / gcd(l, *w.denom())).checked_mul(*w.denom())
+        });
+        let denom = denom.ok_or(AliasTableError::WeightOverflow)?;
+
+        let ints = weights.iter()
+            .map(|w| w.numer().checked_mul(denom / w.denom()))
+            .collect::<Option<Vec<_>>>()
+            .ok_or(AliasTableError::WeightOverflow)?;
+
+        AliasIndexTable::from_integer_weights(&ints)
+    }
+
+    /// Compute the exact probability with which each index is picked, by summing the mass given
+    /// to it by every bucket of the table. For a table built from integer or rational weights,
+    /// these are exactly the normalized weights.
+    pub fn exact_probabilities(&self) -> Vec<Ratio<u128>> {
+        let scale = self.scale as u128;
+        let mut masses = vec![0u128; self.table.len()];
+
+        for entry in &self.table {
+            match *entry {
+                Aliased { threshold, value, alias } => {
+                    masses[value] += threshold as u128;
+                    masses[alias] += scale - threshold as u128;
+                }
+                Unaliased(idx) => masses[idx] += scale,
+            }
+        }
+
+        let total = scale * self.table.len() as u128;
+        masses.into_iter().map(|mass| Ratio::new(mass, total)).collect()
+    }
+}
+
+/// Compute the greatest common divisor of two integers.
+#[cfg(feature = "num-rational")]
+fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        let r = a % b;
+        a = b;
+        b = r;
+    }
+
+    a
+}
+
+#[cfg(feature = "rayon")]
+impl<T, F> AliasTable<T, F>
+    where F: AliasWeight + Send + Sync
+{
+    /// Construct an alias table like `new`, but normalize and partition the weights in parallel
+    /// on the rayon thread pool. Only available with the `rayon` feature.
+    pub fn par_new(objs: Vec<T>, ps: &[F]) -> Result<Self, AliasTableError> {
+        if objs.len() != ps.len() {
+            return Err(AliasTableError::LengthMismatch {
+                values: objs.len(),
+                weights: ps.len(),
+            });
+        }
+
+        AliasIndexTable::par_from_weights(ps)?.with_values(objs)
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl<F> AliasIndexTable<F>
+    where F: AliasWeight + Send + Sync
+{
+    /// Construct an index table like `from_weights`, but validate, normalize and partition the
+    /// weights in parallel on the rayon thread pool. Pairing the partitioned entries off into the
+    /// table is inherently sequential and still runs on the calling thread. Only available with
+    /// the `rayon` feature.
+    pub fn par_from_weights(ps: &[F]) -> Result<Self, AliasTableError> {
+        if ps.is_empty() {
+            return Err(AliasTableError::Empty);
+        }
+
+        ps.par_iter()
+            .enumerate()
+            .map(|(index, &p)| check_weight(index, p))
+            .find_first(Result::is_err)
+            .unwrap_or(Ok(()))?;
+
+        let psum = ps.par_iter().cloned().reduce(F::zero, |a, b| a + b);
+
+        if psum == F::zero() {
+            return Err(AliasTableError::ZeroTotalWeight);
+        } else if !psum.is_finite_weight() {
+            return Err(AliasTableError::WeightOverflow);
+        }
+
+        let pn = F::from_u64(ps.len() as u64).ok_or(AliasTableError::CastFailure)?;
+        let pcoeff = pn / psum;
+
+        let (small, large): (Vec<_>, Vec<_>) = ps.par_iter()
+            .map(|&p| (pcoeff * p, F::zero()))
+            .enumerate()
+            .partition(|&(_, (p, _))| p < F::one());
+
+        Ok(AliasIndexTable::from_worklists(small, large, &BuildOptions::default()))
+    }
+}
+
+#[cfg(feature = "ndarray")]
+impl<T, F> AliasTable<T, F>
+    where F: AliasWeight
+{
+    /// Construct an alias table like `new`, but with the weights given as an `ndarray` view. Only
+    /// available with the `ndarray` feature.
+    pub fn from_weight_view(objs: Vec<T>, ps: ArrayView1<F>) -> Result<Self, AliasTableError> {
+        if objs.len() != ps.len() {
+            return Err(AliasTableError::LengthMismatch {
+                values: objs.len(),
+                weights: ps.len(),
+            });
+        }
+
+        AliasIndexTable::from_weight_view(ps)?.with_values(objs)
+    }
+
+    /// Construct an alias table from an `ndarray` view of values and an `ndarray` view of their
+    /// weights, cloning the values. Only available with the `ndarray` feature.
+    pub fn from_array_views(objs: ArrayView1<T>, ps: ArrayView1<F>) -> Result<Self, AliasTableError>
+        where T: Clone
+    {
+        AliasTable::from_weight_view(objs.to_vec(), ps)
+    }
+}
+
+#[cfg(feature = "ndarray")]
+impl<F> AliasIndexTable<F>
+    where F: AliasWeight
+{
+    /// Construct an index table like `from_weights`, but with the weights given as an `ndarray`
+    /// view. Contiguous views are read in place; views with any other layout are copied into a
+    /// contiguous buffer first. Only available with the `ndarray` feature.
+    pub fn from_weight_view(ps: ArrayView1<F>) -> Result<Self, AliasTableError> {
+        match ps.as_slice() {
+            Some(ps) => AliasIndexTable::from_weights(ps),
+            None => AliasIndexTable::from_weights(&ps.to_vec()),
+        }
+    }
+}
+
+#[cfg(feature = "csv")]
+impl<T, F> AliasTable<T, F>
+    where T: FromStr,
+          F: AliasWeight + FromStr
+{
+    /// Construct an alias table from CSV data with a header row, taking each record's value from
+    /// the column at index `value_col` and its weight from the column at index `weight_col`.
+    /// Fields are trimmed of surrounding whitespace before being parsed. Errors on a particular
+    /// record report the line it starts on. Only available with the `csv` feature.
+    pub fn from_csv_reader<R: Read>(reader: R,
+                                    value_col: usize,
+                                    weight_col: usize)
+                                    -> Result<Self, AliasTableError> {
+        let mut reader = csv::ReaderBuilder::new().trim(csv::Trim::All).from_reader(reader);
+        let mut objs = Vec::new();
+        let mut ps = Vec::new();
+
+        for record in reader.records() {
+            let record = record.map_err(|err| {
+                    AliasTableError::CsvRead {
+                        line: err.position().map(|pos| pos.line()),
+                        message: err.to_string(),
+                    }
+                })?;
+            let line = record.position().map_or(0, |pos| pos.line());
+            let field = |column| {
+                record.get(column)
+                    .ok_or(AliasTableError::CsvField { line, column })
+            };
+
+            objs.push(field(value_col)?
+                .parse()
+                .map_err(|_| AliasTableError::CsvField { line, column: value_col })?);
+            ps.push(field(weight_col)?
+                .parse()
+                .map_err(|_| AliasTableError::CsvField { line, column: weight_col })?);
+        }
+
+        AliasTable::new(objs, &ps)
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl<T, F> AliasTable<T, F>
+    where T: Sync,
+          F: AliasThreshold + Send + Sync
+{
+    /// Pick `n` random elements from the distribution in parallel on the rayon thread pool, as
+    /// described by `AliasIndexTable::par_sample_iter`. Only available with the `rayon` feature.
+    pub fn par_sample_n(&self, n: usize, seed: u64) -> Vec<&T> {
+        self.par_sample_iter(n, seed).collect()
+    }
+
+    /// Produce a parallel iterator of `n` random elements from the distribution, as described by
+    /// `AliasIndexTable::par_sample_iter`. Only available with the `rayon` feature.
+    pub fn par_sample_iter(&self, n: usize, seed: u64) -> impl ParallelIterator<Item = &T> {
+        self.indices.par_sample_iter(n, seed).map(move |idx| &self.objs[idx])
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl<F> AliasIndexTable<F>
+    where F: AliasThreshold + Send + Sync
+{
+    /// Pick `n` random indices from the distribution in parallel on the rayon thread pool, as
+    /// described by `par_sample_iter`. Only available with the `rayon` feature.
+    pub fn par_sample_n(&self, n: usize, seed: u64) -> Vec<usize> {
+        self.par_sample_iter(n, seed).collect()
+    }
+
+    /// Produce a parallel iterator of `n` random indices from the distribution. The picks are
+    /// split into fixed-size chunks, each with its own RNG seeded from `seed` and the chunk's
+    /// position, so the same seed always produces the same picks in the same order, no matter
+    /// how many threads the work is split across. Only available with the `rayon` feature.
+    pub fn par_sample_iter(&self, n: usize, seed: u64) -> impl ParallelIterator<Item = usize> + '_ {
+        const CHUNK: usize = 1 << 14;
+
+        (0..(n + CHUNK - 1) / CHUNK).into_par_iter().flat_map_iter(move |chunk| {
+            let mut rng = chunk_rng(seed, chunk as u64);
+            let len = cmp::min(CHUNK, n - chunk * CHUNK);
+            (0..len).map(move |_| self.pick(&mut rng))
+        })
+    }
+}
+
+/// Seed an RNG for one chunk of a parallel sampling job, by running the job's seed and the
+/// chunk's position through SplitMix64.
+#[cfg(feature = "rayon")]
+fn chunk_rng(seed: u64, chunk: u64) -> XorShiftRng {
+    let mut state = seed ^ chunk.wrapping_mul(0xd1b5_4a32_d192_ed03);
+    let mut next = || {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let z = (state ^ (state >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        let z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    };
+    let (a, b) = (next(), next());
+
+    // An all-zero seed is invalid for xorshift, so set one bit to rule it out.
+    XorShiftRng::from_seed([a as u32, (a >> 32) as u32, b as u32, (b >> 32) as u32 | 1])
+}
+
+/// A worklist entry: an index, its scaled weight, and a compensation term for that weight.
+type Pending<F> = (usize, (F, F));
+
+/// A worklist of entries waiting to be paired. Normally this is a stack, but for canonical
+/// construction it is a heap which pops entries in order of decreasing mass, and then of
+/// decreasing index, so the pairing doesn't depend on the order entries were pushed in.
+enum Worklist<F> {
+    Stack(Vec<Pending<F>>),
+    Heap(BinaryHeap<ByMass<F>>),
+}
+
+/// A worklist entry ordered by mass and then by index.
+struct ByMass<F>(Pending<F>);
+
+impl<F: AliasWeight> Worklist<F> {
+    fn new(entries: Vec<Pending<F>>, canonical: bool) -> Self {
+        if canonical {
+            Worklist::Heap(entries.into_iter().map(ByMass).collect())
+        } else {
+            Worklist::Stack(entries)
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        match *self {
+            Worklist::Stack(ref stack) => stack.is_empty(),
+            Worklist::Heap(ref heap) => heap.is_empty(),
+        }
+    }
+
+    fn push(&mut self, entry: Pending<F>) {
+        match *self {
+            Worklist::Stack(ref mut stack) => stack.push(entry),
+            Worklist::Heap(ref mut heap) => heap.push(ByMass(entry)),
+        }
+    }
+
+    fn pop(&mut self) -> Option<Pending<F>> {
+        match *self {
+            Worklist::Stack(ref mut stack) => stack.pop(),
+            Worklist::Heap(ref mut heap) => heap.pop().map(|ByMass(entry)| entry),
+        }
+    }
+
+    fn into_vec(self) -> Vec<Pending<F>> {
+        match self {
+            Worklist::Stack(stack) => stack,
+            Worklist::Heap(heap) => {
+                heap.into_sorted_vec().into_iter().map(|ByMass(entry)| entry).collect()
+            }
+        }
+    }
+}
+
+impl<F: AliasWeight> Ord for ByMass<F> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        cmp_mass(&self.0, &other.0).then((self.0).0.cmp(&(other.0).0))
+    }
+}
+
+impl<F: AliasWeight> PartialOrd for ByMass<F> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<F: AliasWeight> PartialEq for ByMass<F> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<F: AliasWeight> Eq for ByMass<F> {}
+
+/// Check that a weight is finite and non-negative, reporting it as being at `index` if not.
+fn check_weight<F: AliasWeight>(index: usize, p: F) -> Result<(), AliasTableError> {
+    if !p.is_finite_weight() {
+        Err(AliasTableError::NonFiniteWeight {
+            index,
+            value: p.weight_to_f64(),
+        })
+    } else if p < F::zero() {
+        Err(AliasTableError::NegativeWeight {
+            index,
+            value: p.weight_to_f64(),
+        })
+    } else {
+        Ok(())
+    }
+}
+
+/// Place an entry left over once one of the worklists runs dry. These should all have a mass of
+/// one, but rounding error can leave a few which don't; those with no mass at all are zero-weight
+/// entries, and get an empty bucket aliasing `fallback` so that they can never be picked.
+fn leftover<F: Zero + PartialEq>(idx: usize, p: F, fallback: Option<usize>) -> AliasEntry<F> {
+    match fallback {
+        Some(alias) if p == F::zero() => {
+            Aliased {
+                threshold: p,
+                value: idx,
+                alias,
+            }
+        }
+        _ => Unaliased(idx),
+    }
+}
+
+/// Order worklist entries by their compensated mass.
+fn cmp_mass<F: AliasWeight>(a: &Pending<F>, b: &Pending<F>) -> Ordering {
+    let (_, (p_a, c_a)) = *a;
+    let (_, (p_b, c_b)) = *b;
+    (p_a + c_a).partial_cmp(&(p_b + c_b)).unwrap_or(Ordering::Equal)
+}
+
+/// Add `x` to the running sum `sum` with compensation term `c`, using Neumaier's variant of
+/// Kahan summation. The compensated total is `sum + c`.
+fn neumaier_add<F: AliasWeight>(sum: F, c: F, x: F) -> (F, F) {
+    let t = sum + x;
+
+    if sum.abs_weight() >= x.abs_weight() {
+        (t, c + ((sum - t) + x))
+    } else {
+        (t, c + ((x - t) + sum))
+    }
+}
+
+/// Subtract `x` from the running sum `sum` with compensation term `c`, in the same way as
+/// `neumaier_add`.
+fn neumaier_sub<F: AliasWeight>(sum: F, c: F, x: F) -> (F, F) {
+    let t = sum - x;
+
+    if sum.abs_weight() >= x.abs_weight() {
+        (t, c + ((sum - t) - x))
+    } else {
+        (t, c + (sum - (x + t)))
+    }
+}
+
+/// Give the entry at `index` the share `probability` of the total of `weights`, scaling the
+/// others so that the total is unchanged and they keep their proportions.
+fn reweight<F: Float>(weights: &[F],
+                      index: usize,
+                      probability: F)
+                      -> Result<Vec<F>, AliasTableError> {
+    if index >= weights.len() {
+        return Err(AliasTableError::InvalidParameter { name: "index" });
+    } else if !(probability >= F::zero() && probability <= F::one()) {
+        return Err(AliasTableError::InvalidParameter { name: "probability" });
+    }
+
+    let total = weights.iter().fold(F::zero(), |sum, &w| sum + w);
+    let rest = total - weights[index];
+
+    if rest <= F::zero() && probability < F::one() {
+        return Err(AliasTableError::InvalidParameter { name: "probability" });
+    }
+
+    let total_rest = total * (F::one() - probability);
+    Ok(weights.iter()
+        .enumerate()
+        .map(|(i, &w)| {
+            if i == index {
+                total * probability
+            } else if rest > F::zero() {
+                w / rest * total_rest
+            } else {
+                F::zero()
+            }
+        })
+        .collect())
+}
+
+/// Convert a number to a floating point type, giving NaN if it can't be represented.
+fn to_weight<T: ToPrimitive, F: Float>(x: &T) -> F {
+    x.to_f64().and_then(F::from).unwrap_or_else(F::nan)
+}
+
+/// The rounding error allowed in probabilities recovered from a table of `n` buckets: a few units
+/// of rounding error per bucket.
+fn rounding_tolerance<F: Float>(n: usize) -> F {
+    F::from(4 * n.max(1)).unwrap_or_else(F::infinity) * F::epsilon()
+}
+
+/// Escape a label for use inside a quoted DOT string.
+fn dot_escape(label: &str) -> String {
+    label.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// The contribution `p * ln(p / q)` of one outcome to a Kullback-Leibler divergence, which is
+/// zero when `p` is zero and infinite when only `q` is.
+fn kl_term<F: Float>(p: F, q: F) -> F {
+    if p == F::zero() {
+        F::zero()
+    } else if q == F::zero() {
+        F::infinity()
+    } else {
+        p * (p / q).ln()
+    }
+}
+
+/// Compute `x * ln_y`, treating `0 * -inf` as zero so that impossible outcomes of a pmf with a
+/// probability of zero or one are handled correctly.
+fn xlny<F: Float>(x: F, ln_y: F) -> F {
+    if x == F::zero() {
+        F::zero()
+    } else {
+        x * ln_y
+    }
+}
+
+/// Draw from a binomial distribution with `n` trials and success probability `p`. Large `n` is
+/// split in half repeatedly using the order statistics of the uniform distribution (Knuth, TAOCP
+/// volume 2, section 3.4.1), which are beta distributed, until few enough trials are left to
+/// simulate one by one.
+fn binomial<R: Rng>(rng: &mut R, mut n: u64, mut p: f64) -> u64 {
+    const DIRECT: u64 = 16;
+
+    let mut successes = 0;
+
+    while n > DIRECT {
+        // The `a`th smallest of `n` uniform variates follows `Beta(a, n + 1 - a)`.
+        let a = 1 + n / 2;
+        let b = n + 1 - a;
+        let x = Gamma::new(a as f64, 1.0).ind_sample(rng);
+        let y = Gamma::new(b as f64, 1.0).ind_sample(rng);
+        let x = x / (x + y);
+
+        if x >= p {
+            n = a - 1;
+            p /= x;
+        } else {
+            successes += a;
+            n = b - 1;
+            p = (p - x) / (1.0 - x);
+        }
+    }
+
+    for _ in 0..n {
+        if rng.gen::<f64>() < p {
+            successes += 1;
+        }
+    }
+
+    successes
+}
+
+/// The natural logarithm of the gamma function for `x > 0`, by the Lanczos approximation with
+/// `g = 7`, which is accurate to about 15 significant digits.
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFS: [f64; 9] = [0.999_999_999_999_809_9,
+                              676.520_368_121_885_1,
+                              -1_259.139_216_722_402_8,
+                              771.323_428_777_653_1,
+                              -176.615_029_162_140_6,
+                              12.507_343_278_686_905,
+                              -0.138_571_095_265_720_12,
+                              9.984_369_578_019_572e-6,
+                              1.505_632_735_149_311_6e-7];
+
+    if x < 0.5 {
+        // The reflection formula, since the approximation is only accurate for `x >= 0.5`.
+        let pi = ::std::f64::consts::PI;
+        return (pi / (pi * x).sin()).ln() - ln_gamma(1.0 - x);
+    }
+
+    let x = x - 1.0;
+    let t = x + 7.5;
+    let sum = COEFFS[1..]
+        .iter()
+        .enumerate()
+        .fold(COEFFS[0], |sum, (i, &c)| sum + c / (x + i as f64 + 1.0));
+
+    0.5 * (2.0 * ::std::f64::consts::PI).ln() + (x + 0.5) * t.ln() - t + sum.ln()
+}
+
+/// The regularized upper incomplete gamma function `Q(a, x)`, the probability that a chi-square
+/// variate with `2a` degrees of freedom exceeds `2x`. Uses the power series of `P(a, x)` below
+/// `x = a + 1`, and a continued fraction evaluated by Lentz's method above it (Numerical
+/// Recipes, section 6.2).
+fn upper_gamma_q(a: f64, x: f64) -> f64 {
+    const EPSILON: f64 = 1e-15;
+    const TINY: f64 = 1e-300;
+    const MAX_ITERATIONS: usize = 1000;
+
+    if x.is_nan() {
+        return f64::NAN;
+    } else if x <= 0.0 {
+        return 1.0;
+    } else if x == f64::INFINITY {
+        return 0.0;
+    }
+
+    let prefix = (a * x.ln() - x - ln_gamma(a)).exp();
+
+    if x < a + 1.0 {
+        let mut term = 1.0 / a;
+        let mut sum = term;
+
+        for n in 1..MAX_ITERATIONS {
+            term *= x / (a + n as f64);
+            sum += term;
+
+            if term.abs() < sum.abs() * EPSILON {
+                break;
+            }
+        }
+
+        (1.0 - sum * prefix).max(0.0)
+    } else {
+        let mut b = x + 1.0 - a;
+        let mut c = 1.0 / TINY;
+        let mut d = 1.0 / b;
+        let mut h = d;
+
+        for n in 1..MAX_ITERATIONS {
+            let an = -(n as f64) * (n as f64 - a);
+            b += 2.0;
+            d = an * d + b;
+            d = if d.abs() < TINY { TINY } else { d };
+            c = b + an / c;
+            c = if c.abs() < TINY { TINY } else { c };
+            d = 1.0 / d;
+            let delta = d * c;
+            h *= delta;
+
+            if (delta - 1.0).abs() < EPSILON {
+                break;
+            }
+        }
+
+        (prefix * h).min(1.0)
+    }
+}
+
+impl<T, F> FromIterator<(T, F)> for AliasTable<T, F>
+    where F: AliasWeight
+{
+    /// Construct an alias table from an iterator. Expects a tuple, where the left-hand element is
+    /// the distribution's value, and the right-hand element is the value's weight in the distribution.
+    ///
+    /// Panics if the weights don't describe a distribution; use `try_from_iter` to handle this.
+    fn from_iter<I: IntoIterator<Item = (T, F)>>(iter: I) -> Self {
+        AliasTable::try_from_iter(iter)
+            .unwrap_or_else(|err| panic!("Error constructing AliasTable<T, F>: {}", err))
+    }
+}
+
+impl<T, F> AliasTableBuilder<T, F> {
+    /// Create an empty builder.
+    pub fn new() -> Self {
+        AliasTableBuilder {
+            objs: Vec::new(),
+            weights: Vec::new(),
+            options: BuildOptions::default(),
+        }
+    }
+
+    /// Use compensated (Neumaier) summation when normalizing the weights and when updating the
+    /// remaining mass of aliased entries. This is slower, but keeps rounding error from
+    /// accumulating when building tables from very many small weights. Off by default, and
+    /// ignored for weight types which can't represent negative values.
+    pub fn compensated_summation(mut self, enabled: bool) -> Self {
+        self.options.compensated = enabled;
+        self
+    }
+
+    /// Choose how entries with a weight of exactly zero are treated. See `ZeroWeights`.
+    pub fn zero_weights(mut self, policy: ZeroWeights) -> Self {
+        self.options.zero_weights = policy;
+        self
+    }
+
+    /// Store the values in order of decreasing weight, so that the most likely values share the
+    /// first few cache lines. This can speed up picking from very large, highly skewed tables.
+    /// Use `AliasTable::original_index` to find the index each value was pushed at. Off by default.
+    pub fn hot_first(mut self, enabled: bool) -> Self {
+        self.options.hot_first = enabled;
+        self
+    }
+
+    /// Keep a copy of the weights in the built table, so that `AliasTable::weights` returns them
+    /// exactly as they were pushed, without the rounding error of recovering them from the table.
+    /// Entries dropped by `ZeroWeights::Drop` are not kept. Off by default.
+    pub fn retain_weights(mut self, enabled: bool) -> Self {
+        self.options.retain_weights = enabled;
+        self
+    }
+
+    /// Create an empty builder with space for at least `capacity` entries.
+    pub fn with_capacity(capacity: usize) -> Self {
+        AliasTableBuilder {
+            objs: Vec::with_capacity(capacity),
+            weights: Vec::with_capacity(capacity),
+            options: BuildOptions::default(),
+        }
+    }
+
+    /// Add a value to the distribution with the given weight.
+    pub fn push(&mut self, value: T, weight: F) -> &mut Self {
+        self.objs.push(value);
+        self.weights.push(weight);
+        self
+    }
+
+    /// Reserve space for at least `additional` more entries.
+    pub fn reserve(&mut self, additional: usize) -> &mut Self {
+        self.objs.reserve(additional);
+        self.weights.reserve(additional);
+        self
+    }
+}
+
+impl<T, F> AliasTableBuilder<T, F>
+    where F: AliasWeight
+{
+    /// Build an alias table from the entries pushed so far.
+    pub fn build(self) -> Result<AliasTable<T, F>, AliasTableError> {
+        let AliasTableBuilder { mut objs, mut weights, options } = self;
+        let mut positions: Vec<usize> = if options.hot_first {
+            (0..weights.len()).collect()
+        } else {
+            Vec::new()
+        };
+
+        if options.zero_weights == ZeroWeights::Drop && weights.contains(&F::zero()) {
+            if weights.iter().all(|&p| p == F::zero()) {
+                return Err(AliasTableError::ZeroTotalWeight);
+            }
+
+            positions.retain(|&i| weights[i] != F::zero());
+
+            let (kept_objs, kept_weights) = objs.into_iter()
+                .zip(weights)
+                .filter(|&(_, p)| p != F::zero())
+                .unzip();
+            objs = kept_objs;
+            weights = kept_weights;
+        }
+
+        let mut table = AliasIndexTable::from_weights_with(&weights, &options)?.with_values(objs)?;
+
+        if options.hot_first {
+            if options.retain_weights {
+                table.weights = Some(weights.clone());
+            }
+
+            table.reorder_hot_first(&weights, positions);
+        } else if options.retain_weights {
+            table.weights = Some(weights);
+        }
+
+        Ok(table)
+    }
+}
+
+impl<T, F> AliasTableBuilder<T, F>
+    where T: Ord,
+          F: AliasWeight
+{
+    /// Build an alias table in canonical form. The entries are sorted by value (and then by
+    /// weight) before construction, and the worklists are kept sorted by mass while pairing, so
+    /// the same multiset of `(value, weight)` tuples always produces an identical table no matter
+    /// what order the entries were pushed in.
+    pub fn build_canonical(mut self) -> Result<AliasTable<T, F>, AliasTableError> {
+        let mut entries: Vec<_> = self.objs.into_iter().zip(self.weights).collect();
+        entries.sort_by(|a, b| {
+            a.0.cmp(&b.0).then_with(|| a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal))
+        });
+
+        let (objs, weights) = entries.into_iter().unzip();
+        self.objs = objs;
+        self.weights = weights;
+        self.options.canonical = true;
+        self.build()
+    }
+}
+
+impl<T, F> Default for AliasTableBuilder<T, F> {
+    fn default() -> Self {
+        AliasTableBuilder::new()
+    }
+}
+
+impl<T, F> Extend<(T, F)> for AliasTableBuilder<T, F> {
+    /// Add every `(value, weight)` tuple from an iterator to the distribution.
+    fn extend<I: IntoIterator<Item = (T, F)>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+        self.reserve(iter.size_hint().0);
+
+        for (value, weight) in iter {
+            self.push(value, weight);
+        }
+    }
+}
+
+impl<T, F, S> From<HashMap<T, F, S>> for AliasTable<T, F>
+    where F: AliasWeight
+{
+    /// Construct an alias table from a map of values to their weights. The order of the values in
+    /// the table follows the map's iteration order, which is unspecified for a `HashMap`.
+    ///
+    /// Panics if the weights don't describe a distribution; use `try_from_iter` to handle this.
+    fn from(map: HashMap<T, F, S>) -> Self {
+        map.into_iter().collect()
+    }
+}
+
+impl<T, F> From<BTreeMap<T, F>> for AliasTable<T, F>
+    where F: AliasWeight
+{
+    /// Construct an alias table from a map of values to their weights. The values are stored in
+    /// ascending order, so the same map always produces the same table.
+    ///
+    /// Panics if the weights don't describe a distribution; use `try_from_iter` to handle this.
+    fn from(map: BTreeMap<T, F>) -> Self {
+        map.into_iter().collect()
+    }
+}
+
+impl<T, F> TryFrom<Vec<(T, F)>> for AliasTable<T, F>
+    where F: AliasWeight
+{
+    type Error = AliasTableError;
+
+    /// Construct an alias table from a vector of `(value, weight)` tuples, like `try_from_iter`.
+    fn try_from(pairs: Vec<(T, F)>) -> Result<Self, Self::Error> {
+        AliasTable::try_from_iter(pairs)
+    }
+}
+
+impl<'a, T, F> TryFrom<&'a [(T, F)]> for AliasTable<T, F>
+    where T: Clone,
+          F: AliasWeight
+{
+    type Error = AliasTableError;
+
+    /// Construct an alias table from a slice of `(value, weight)` tuples, cloning the values,
+    /// like `try_from_iter`.
+    fn try_from(pairs: &'a [(T, F)]) -> Result<Self, Self::Error> {
+        AliasTable::try_from_iter(pairs.iter().cloned())
+    }
+}
+
+impl<T, F> From<AliasTable<T, F>> for AliasIndexTable<F> {
+    /// Discard the values of an alias table, keeping only the distribution over their indices.
+    fn from(table: AliasTable<T, F>) -> Self {
+        table.indices
+    }
+}
+
+impl<T, F> PartialEq for AliasTable<T, F>
+    where T: PartialEq,
+          F: AliasThreshold + Float
+{
+    /// Compare tables by the distributions they describe rather than by their buckets: tables
+    /// are equal if they store equal values in the same order and pick them with the same
+    /// probabilities, as described by `AliasIndexTable::eq`.
+    fn eq(&self, other: &Self) -> bool {
+        self.objs == other.objs && self.indices == other.indices
+    }
+}
+
+impl<F> PartialEq for AliasIndexTable<F>
+    where F: AliasThreshold + Float
+{
+    /// Compare tables by the distributions they describe rather than by their buckets: tables
+    /// are equal if they have the same number of indices, and every index has the same
+    /// probability in both, up to a few units of rounding error per bucket. Because of the
+    /// tolerance, this comparison is not transitive; use `approx_eq` to choose the tolerance.
+    fn eq(&self, other: &Self) -> bool {
+        let tolerance = rounding_tolerance(self.table.len());
+
+        self.table.len() == other.table.len() &&
+        self.cached_probabilities()
+            .iter()
+            .zip(other.cached_probabilities())
+            .all(|(&p, &q)| (p - q).abs() <= tolerance)
+    }
+}
+
+impl<T, F> Index<usize> for AliasTable<T, F> {
+    type Output = T;
+
+    /// The value at `index`, in the order used by `pick_index` and `fill_indices`, so that
+    /// sampled indices can be resolved back to their values. Panics if `index` is out of bounds.
+    fn index(&self, index: usize) -> &T {
+        &self.objs[index]
+    }
+}
+
+impl<'a, T: 'a, F, R> Iterator for AliasTableIterator<'a, T, F, R>
+    where F: AliasThreshold,
+          R: Rng
+{
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.table.pick(&mut self.rng))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (usize::MAX, None)
+    }
+}
+
+impl<'a, F, R> Iterator for AliasIndexIterator<'a, F, R>
+    where F: AliasThreshold,
+          R: Rng
+{
+    type Item = usize;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.table.pick(&mut self.rng))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (usize::MAX, None)
+    }
+}
+
+impl<'a, T: 'a, F, R> Iterator for NoRepeatIterator<'a, T, F, R>
+    where F: AliasThreshold + Float,
+          R: Rng
+{
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let idx = match self.last {
+            Some(last) => self.table.indices.pick_excluding(&mut self.rng, &[last])?,
+            None => self.table.pick_index(&mut self.rng),
+        };
+
+        self.last = Some(idx);
+        Some(&self.table.objs[idx])
+    }
+}
+
+impl<T, F, R> Iterator for AliasTableIntoIterator<T, F, R>
+    where T: Clone,
+          F: AliasThreshold,
+          R: Rng
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.table.sample(&mut self.rng))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (usize::MAX, None)
+    }
+}
+
+impl<'a, T: 'a, F, R> Iterator for AliasTableBoundedIterator<'a, T, F, R>
+    where F: AliasThreshold,
+          R: Rng
+{
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+
+        self.remaining -= 1;
+        Some(self.table.pick(&mut self.rng))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a, T: 'a, F, R> ExactSizeIterator for AliasTableBoundedIterator<'a, T, F, R>
+    where F: AliasThreshold,
+          R: Rng
+{
+}
+
+impl<'a, T, F> IntoIterator for &'a AliasTable<T, F>
+    where F: AliasThreshold
+{
+    type Item = &'a T;
+    type IntoIter = AliasTableIterator<'a, T, F, rand::ThreadRng>;
+
+    /// Produces an iterator that picks random element from the distribution by calling
+    /// calling `pick` repeatedly with the thread's RNG.
     fn into_iter(self) -> Self::IntoIter {
         self.iter(rand::thread_rng())
     }
 }
+
+impl<F> Sample<usize> for AliasIndexTable<F>
+    where F: AliasThreshold
+{
+    fn sample<R: Rng>(&mut self, rng: &mut R) -> usize {
+        self.pick(rng)
+    }
+}
+
+impl<F> IndependentSample<usize> for AliasIndexTable<F>
+    where F: AliasThreshold
+{
+    /// Pick a random index from the distribution, like `pick`.
+    fn ind_sample<R: Rng>(&self, rng: &mut R) -> usize {
+        self.pick(rng)
+    }
+}
+
+impl<T, F> Sample<T> for AliasTable<T, F>
+    where T: Clone,
+          F: AliasThreshold
+{
+    fn sample<R: Rng>(&mut self, rng: &mut R) -> T {
+        self.pick(rng).clone()
+    }
+}
+
+impl<T, F> IndependentSample<T> for AliasTable<T, F>
+    where T: Clone,
+          F: AliasThreshold
+{
+    /// Pick a random element from the distribution, like `sample`. To sample indices instead, use
+    /// the table's `AliasIndexTable`.
+    fn ind_sample<R: Rng>(&self, rng: &mut R) -> T {
+        self.pick(rng).clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, HashMap};
+    use std::convert::TryFrom;
+
+    use std::ops::{Add, Div, Mul, Sub};
+
+    use num_traits::{One, Zero};
+    use rand::{Rng, SeedableRng, XorShiftRng};
+
+    use super::{binomial, ln_gamma, upper_gamma_q};
+    use {AliasIndexTable, AliasTable, AliasTableBuilder, AliasTableError, AliasThreshold,
+         AliasWeight, BatchIndexTable, DynamicAliasTable, StaticAliasTable, ZeroWeights};
+
+    const TOLERANCE: f64 = 1e-12;
+
+    fn rng() -> XorShiftRng {
+        XorShiftRng::from_seed([0x193a_6754, 0xa8a7_d469, 0x9783_0e05, 0x113b_a7bb])
+    }
+
+    fn normalize(weights: &[f64]) -> Vec<f64> {
+        let total: f64 = weights.iter().sum();
+        weights.iter().map(|&w| w / total).collect()
+    }
+
+    fn assert_close(actual: &[f64], expected: &[f64]) {
+        assert_eq!(actual.len(), expected.len());
+
+        for (i, (&a, &e)) in actual.iter().zip(expected).enumerate() {
+            assert!((a - e).abs() <= TOLERANCE,
+                    "probability {} is {} rather than {}",
+                    i,
+                    a,
+                    e);
+        }
+    }
+
+    fn assert_recovers<T>(table: &AliasTable<T, f64>, weights: &[f64]) {
+        assert!(table.validate().is_valid(), "{:?}", table.validate());
+        assert_close(&table.probabilities(), &normalize(weights));
+    }
+
+    fn assert_index_recovers(table: &AliasIndexTable<f64>, weights: &[f64]) {
+        assert!(table.validate().is_valid(), "{:?}", table.validate());
+        assert_close(&table.probabilities(), &normalize(weights));
+    }
+
+    const WEIGHTS: [f64; 6] = [1.0, 2.0, 0.5, 0.0, 7.25, 3.0];
+
+    fn values() -> Vec<char> {
+        "abcdef".chars().collect()
+    }
+
+    fn pairs() -> Vec<(char, f64)> {
+        values().into_iter().zip(WEIGHTS.iter().cloned()).collect()
+    }
+
+    #[test]
+    fn new_recovers_weights() {
+        assert_recovers(&AliasTable::new(values(), &WEIGHTS).unwrap(), &WEIGHTS);
+        assert_index_recovers(&AliasIndexTable::from_weights(&WEIGHTS).unwrap(), &WEIGHTS);
+    }
+
+    #[test]
+    fn iterator_constructors_recover_weights() {
+        assert_recovers(&AliasTable::try_from_iter(pairs()).unwrap(), &WEIGHTS);
+        assert_recovers(&AliasTable::from_exact_size_iter(pairs()).unwrap(), &WEIGHTS);
+        assert_recovers(&pairs().into_iter().collect::<AliasTable<_, _>>(), &WEIGHTS);
+        assert_recovers(&AliasTable::try_from(pairs()).unwrap(), &WEIGHTS);
+        assert_recovers(&AliasTable::try_from(&pairs()[..]).unwrap(), &WEIGHTS);
+
+        let total = WEIGHTS.iter().sum();
+        assert_recovers(&AliasTable::from_pairs_with_total(pairs(), total).unwrap(), &WEIGHTS);
+    }
+
+    #[test]
+    fn from_exact_size_iter_matches_new() {
+        let weights: Vec<f64> = (0..1000).map(|i| ((i * 7919) % 113) as f64).collect();
+        let pairs = weights.iter().cloned().enumerate();
+        let streamed = AliasTable::from_exact_size_iter(pairs).unwrap();
+        let table = AliasTable::new((0..1000).collect(), &weights).unwrap();
+
+        assert_recovers(&streamed, &weights);
+        assert_close(&streamed.probabilities(), &table.probabilities());
+    }
+
+    #[test]
+    fn from_probabilities_checks_the_sum() {
+        let ps = normalize(&WEIGHTS);
+        assert_recovers(&AliasTable::from_probabilities(values(), &ps, 1e-9).unwrap(),
+                        &WEIGHTS);
+
+        match AliasTable::from_probabilities(values(), &WEIGHTS, 1e-9) {
+            Err(AliasTableError::NotNormalized { .. }) => {}
+            other => panic!("expected NotNormalized, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn weight_function_constructors_recover_weights() {
+        let from_fn = AliasTable::from_fn(0..6, |&i| WEIGHTS[i]).unwrap();
+        assert_recovers(&from_fn, &WEIGHTS);
+
+        let counts = AliasTable::<_, f64>::from_counts(vec![('a', 3), ('b', 0), ('c', 5)]).unwrap();
+        assert_recovers(&counts, &[3.0, 0.0, 5.0]);
+
+        let cdf = AliasTable::from_cdf(vec!['a', 'b', 'c', 'd'], &[1.0, 3.0, 3.0, 6.0]).unwrap();
+        assert_recovers(&cdf, &[1.0, 2.0, 0.0, 3.0]);
+        assert_eq!(AliasIndexTable::from_cdf(&[1.0, 3.0, 2.0]).unwrap_err(),
+                   AliasTableError::NonMonotoneCdf { index: 2 });
+
+        let uniform = AliasTable::<_, f64>::uniform(values()).unwrap();
+        assert_recovers(&uniform, &[1.0; 6]);
+    }
+
+    #[test]
+    fn log_space_constructors_recover_weights() {
+        let logs: Vec<f64> = WEIGHTS.iter().map(|w| w.ln()).collect();
+        assert_recovers(&AliasTable::from_log_weights(values(), &logs).unwrap(), &WEIGHTS);
+
+        let shifted: Vec<f64> = logs.iter().map(|l| l - 2000.0).collect();
+        assert_recovers(&AliasTable::from_log_weights(values(), &shifted).unwrap(), &WEIGHTS);
+
+        let logits: Vec<f64> = logs.iter().map(|l| l * 0.5).collect();
+        assert_recovers(&AliasTable::from_logits(values(), &logits, 0.5).unwrap(), &WEIGHTS);
+        assert_eq!(AliasTable::from_logits(values(), &logits, 0.0).unwrap_err(),
+                   AliasTableError::InvalidParameter { name: "temperature" });
+    }
+
+    #[test]
+    fn named_distributions_recover_weights() {
+        let zipf: Vec<f64> = (1..11).map(|k| (k as f64).powf(-1.5)).collect();
+        assert_index_recovers(&AliasIndexTable::zipf(10, 1.5).unwrap(), &zipf);
+        assert_recovers(&AliasTable::zipf_with((0..10).collect(), 1.5).unwrap(), &zipf);
+
+        let mut factorial = 1.0;
+        let poisson: Vec<f64> = (0..21)
+            .map(|k| {
+                if k > 0 {
+                    factorial *= k as f64;
+                }
+                3.5f64.powi(k) / factorial
+            })
+            .collect();
+        assert_recovers(&AliasTable::poisson(3.5, 20).unwrap(), &poisson);
+
+        let mut choose = 1.0;
+        let binomial: Vec<f64> = (0..13)
+            .map(|k| {
+                if k > 0 {
+                    choose *= (13 - k) as f64 / k as f64;
+                }
+                choose * 0.3f64.powi(k) * 0.7f64.powi(12 - k)
+            })
+            .collect();
+        assert_recovers(&AliasTable::binomial(12, 0.3).unwrap(), &binomial);
+        assert_recovers(&AliasTable::binomial(4, 1.0).unwrap(), &[0.0, 0.0, 0.0, 0.0, 1.0]);
+
+        let geometric: Vec<f64> = (0..16).map(|k| 0.25 * 0.75f64.powi(k)).collect();
+        assert_recovers(&AliasTable::geometric(0.25, 15).unwrap(), &geometric);
+
+        assert_index_recovers(&AliasIndexTable::uniform(7).unwrap(), &[1.0; 7]);
+    }
+
+    /// An unsigned fixed-point number with 16 fractional bits, which can't represent the
+    /// negative correction terms of compensated summation.
+    #[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+    struct Fixed(u64);
+
+    impl Add for Fixed {
+        type Output = Fixed;
+
+        fn add(self, other: Fixed) -> Fixed {
+            Fixed(self.0 + other.0)
+        }
+    }
+
+    impl Sub for Fixed {
+        type Output = Fixed;
+
+        fn sub(self, other: Fixed) -> Fixed {
+            Fixed(self.0 - other.0)
+        }
+    }
+
+    impl Mul for Fixed {
+        type Output = Fixed;
+
+        fn mul(self, other: Fixed) -> Fixed {
+            Fixed(((self.0 as u128 * other.0 as u128) >> 16) as u64)
+        }
+    }
+
+    impl Div for Fixed {
+        type Output = Fixed;
+
+        fn div(self, other: Fixed) -> Fixed {
+            Fixed((((self.0 as u128) << 16) / other.0 as u128) as u64)
+        }
+    }
+
+    impl Zero for Fixed {
+        fn zero() -> Fixed {
+            Fixed(0)
+        }
+
+        fn is_zero(&self) -> bool {
+            self.0 == 0
+        }
+    }
+
+    impl One for Fixed {
+        fn one() -> Fixed {
+            Fixed(1 << 16)
+        }
+    }
+
+    impl AliasThreshold for Fixed {
+        fn sample_below<R: Rng>(scale: Fixed, rng: &mut R) -> Fixed {
+            Fixed(((rng.next_u64() as u128 * scale.0 as u128) >> 64) as u64)
+        }
+    }
+
+    impl AliasWeight for Fixed {
+        fn from_u64(n: u64) -> Option<Fixed> {
+            n.checked_mul(1 << 16).map(Fixed)
+        }
+
+        fn weight_to_f64(self) -> f64 {
+            self.0 as f64 / 65536.0
+        }
+    }
+
+    #[test]
+    fn compensated_summation_skips_unsigned_weights() {
+        let build = |compensated| {
+            let mut builder = AliasTableBuilder::new().compensated_summation(compensated);
+            builder.extend(values().into_iter().zip([3, 1, 2, 0, 7, 5].iter().map(|&w| {
+                Fixed(w << 16)
+            })));
+            builder.build().unwrap()
+        };
+
+        let plain = build(false);
+        let compensated = build(true);
+        let (mut plain_rng, mut compensated_rng) = (rng(), rng());
+
+        for _ in 0..1000 {
+            let pick = *plain.pick(&mut plain_rng);
+            assert_ne!(pick, 'd');
+            assert_eq!(*compensated.pick(&mut compensated_rng), pick);
+        }
+    }
+
+    #[test]
+    fn rebuild_in_place_recovers_new_weights() {
+        let mut table = AliasTable::new(values(), &WEIGHTS).unwrap();
+        let new_weights = [4.0, 0.0, 1.0, 1.0, 2.0, 0.125];
+
+        table.rebuild_in_place(&new_weights).unwrap();
+        assert_recovers(&table, &new_weights);
+
+        assert_eq!(table.rebuild_in_place(&[1.0, -1.0, 1.0, 1.0, 1.0, 1.0]).unwrap_err(),
+                   AliasTableError::NegativeWeight { index: 1, value: -1.0 });
+        assert_recovers(&table, &new_weights);
+    }
+
+    #[test]
+    fn builder_options_recover_weights() {
+        let build = |builder: AliasTableBuilder<char, f64>| {
+            let mut builder = builder;
+            builder.extend(pairs());
+            builder.build()
+        };
+
+        assert_recovers(&build(AliasTableBuilder::new()).unwrap(), &WEIGHTS);
+        assert_recovers(&build(AliasTableBuilder::new().compensated_summation(true)).unwrap(),
+                        &WEIGHTS);
+        assert_recovers(&build(AliasTableBuilder::new().zero_weights(ZeroWeights::Keep))
+                            .unwrap(),
+                        &WEIGHTS);
+
+        let dropped = build(AliasTableBuilder::new().zero_weights(ZeroWeights::Drop)).unwrap();
+        assert_eq!(dropped.values(), &['a', 'b', 'c', 'e', 'f']);
+        assert_recovers(&dropped, &[1.0, 2.0, 0.5, 7.25, 3.0]);
+
+        assert_eq!(build(AliasTableBuilder::new().zero_weights(ZeroWeights::Reject)).unwrap_err(),
+                   AliasTableError::ZeroWeight { index: 3 });
+
+        let retained = build(AliasTableBuilder::new().retain_weights(true)).unwrap();
+        assert_eq!(retained.weights(), Some(&WEIGHTS[..]));
+        assert_recovers(&retained, &WEIGHTS);
+    }
+
+    #[test]
+    fn hot_first_keeps_original_indices() {
+        let mut builder = AliasTableBuilder::new().hot_first(true).retain_weights(true);
+        builder.extend(pairs());
+        let table = builder.build().unwrap();
+        let ps = table.probabilities();
+        let expected = normalize(&WEIGHTS);
+
+        assert!(table.validate().is_valid());
+        assert_eq!(table.values()[0], 'e');
+
+        for (i, &value) in table.values().iter().enumerate() {
+            let original = table.original_index(i);
+            assert_eq!(value, values()[original]);
+            assert!((ps[i] - expected[original]).abs() <= TOLERANCE);
+            assert_eq!(table.weights().unwrap()[i], WEIGHTS[original]);
+        }
+    }
+
+    #[test]
+    fn canonical_tables_ignore_push_order() {
+        let mut forward = AliasTableBuilder::new();
+        forward.extend(pairs());
+        let mut backward = AliasTableBuilder::new();
+        backward.extend(pairs().into_iter().rev());
+
+        let forward = forward.build_canonical().unwrap();
+        let backward = backward.build_canonical().unwrap();
+
+        assert_eq!(format!("{:?}", forward), format!("{:?}", backward));
+        assert_recovers(&forward, &WEIGHTS);
+    }
+
+    #[test]
+    fn merging_constructors_sum_repeated_values() {
+        let repeated = vec![('a', 1.0), ('b', 2.0), ('a', 3.0), ('c', 0.5), ('b', 1.0)];
+        let dedup = AliasTable::from_iter_dedup(repeated).unwrap();
+        assert_eq!(dedup.values(), &['a', 'b', 'c']);
+        assert_recovers(&dedup, &[4.0, 3.0, 0.5]);
+
+        let samples = AliasTable::<_, f64>::from_samples("abracadabra".chars()).unwrap();
+        assert_eq!(samples.values(), &['a', 'b', 'r', 'c', 'd']);
+        assert_recovers(&samples, &[5.0, 2.0, 2.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn maps_recover_weights() {
+        let map: HashMap<char, f64> = pairs().into_iter().collect();
+        let table = AliasTable::from(map.clone());
+        let expected: Vec<f64> = table.values().iter().map(|value| map[value]).collect();
+        assert_recovers(&table, &expected);
+
+        let sorted: BTreeMap<char, f64> = pairs().into_iter().rev().collect();
+        let table = AliasTable::from(sorted);
+        assert_eq!(table.values(), &values()[..]);
+        assert_recovers(&table, &WEIGHTS);
+    }
+
+    #[test]
+    fn updates_recover_weights() {
+        let mut table = AliasTable::new(values(), &WEIGHTS).unwrap();
+        table.set_weight(3, 1.0).unwrap();
+        let mut expected = normalize(&WEIGHTS);
+        expected[3] = 1.0;
+        assert_recovers(&table, &expected);
+
+        assert_eq!(table.set_weight(6, 1.0).unwrap_err(),
+                   AliasTableError::InvalidParameter { name: "index" });
+        assert_recovers(&table, &expected);
+
+        table.set_probability(1, 0.5).unwrap();
+        let ps = table.probabilities();
+        assert!((ps[1] - 0.5).abs() <= TOLERANCE);
+
+        table.set_probability(1, 0.5).unwrap();
+        assert_close(&table.probabilities(), &ps);
+
+        let mut builder = AliasTableBuilder::new().retain_weights(true);
+        builder.extend(pairs());
+        let mut table = builder.build().unwrap();
+        table.set_weight(3, 2.0).unwrap();
+        table.push('g', 4.0).unwrap();
+
+        let weights = [1.0, 2.0, 0.5, 2.0, 7.25, 3.0, 4.0];
+        assert_eq!(table.weights(), Some(&weights[..]));
+        assert_recovers(&table, &weights);
+
+        assert_eq!(table.remove(4), Ok('e'));
+        assert_recovers(&table, &[1.0, 2.0, 0.5, 2.0, 3.0, 4.0]);
+    }
+
+    #[test]
+    fn integer_weights_are_checked() {
+        let table = AliasIndexTable::from_integer_weights(&[3u32, 0, 5]).unwrap();
+        let mut rng = rng();
+        let mut counts = [0usize; 3];
+
+        for _ in 0..80_000 {
+            counts[table.pick(&mut rng)] += 1;
+        }
+
+        assert_eq!(counts[1], 0);
+        assert!(counts[0] > 29_000 && counts[0] < 31_000, "{:?}", counts);
+
+        assert_eq!(AliasIndexTable::from_integer_weights::<u64>(&[]).unwrap_err(),
+                   AliasTableError::Empty);
+        assert_eq!(AliasIndexTable::from_integer_weights(&[0u8, 0]).unwrap_err(),
+                   AliasTableError::ZeroTotalWeight);
+        assert_eq!(AliasIndexTable::from_integer_weights(&[u64::MAX, 1]).unwrap_err(),
+                   AliasTableError::WeightOverflow);
+        assert_eq!(AliasIndexTable::from_integer_weights(&[1u128 << 64]).unwrap_err(),
+                   AliasTableError::WeightOverflow);
+    }
+
+    #[test]
+    fn static_tables_pick_every_entry() {
+        static TABLE: StaticAliasTable<char, 4> = StaticAliasTable::new(['a', 'b', 'c', 'd'],
+                                                                        [1, 0, 6, 3]);
+        let mut rng = rng();
+        let mut counts = [0usize; 4];
+
+        for _ in 0..100_000 {
+            counts[TABLE.pick_index(&mut rng)] += 1;
+        }
+
+        assert_eq!(counts[1], 0);
+        assert!(counts[0] > 9_000 && counts[0] < 11_000, "{:?}", counts);
+        assert!(counts[2] > 59_000 && counts[2] < 61_000, "{:?}", counts);
+    }
+
+    #[test]
+    fn batch_tables_follow_the_distribution() {
+        let table = AliasIndexTable::from_weights(&WEIGHTS).unwrap();
+        let batch = BatchIndexTable::try_from(&table).unwrap();
+        let mut out = vec![0; 200_000];
+        batch.fill_indices(&mut rng(), &mut out);
+
+        let mut counts = [0usize; 6];
+
+        for &idx in &out {
+            counts[idx] += 1;
+        }
+
+        for (&count, &p) in counts.iter().zip(&normalize(&WEIGHTS)) {
+            let expected = p * out.len() as f64;
+            assert!((count as f64 - expected).abs() <= 6.0 * expected.sqrt(), "{:?}", counts);
+        }
+    }
+
+    #[test]
+    fn binomial_splitting_matches_moments() {
+        let (n, p) = (1_000_000u64, 0.3);
+        let draws = 4000;
+        let mut rng = rng();
+        let samples: Vec<f64> = (0..draws).map(|_| binomial(&mut rng, n, p) as f64).collect();
+
+        let mean = samples.iter().sum::<f64>() / draws as f64;
+        let variance = samples.iter().map(|x| (x - mean).powi(2)).sum::<f64>() /
+                       (draws - 1) as f64;
+        let expected_variance = n as f64 * p * (1.0 - p);
+
+        // Six standard errors of the mean, and about six of the sample variance.
+        assert!((mean - n as f64 * p).abs() < 6.0 * (expected_variance / draws as f64).sqrt(),
+                "mean {}",
+                mean);
+        assert!((variance / expected_variance - 1.0).abs() < 0.15,
+                "variance {}",
+                variance);
+
+        assert_eq!(binomial(&mut rng, n, 0.0), 0);
+        assert_eq!(binomial(&mut rng, n, 1.0), n);
+    }
+
+    #[test]
+    fn sample_counts_sum_to_draws() {
+        let table = AliasTable::new(values(), &WEIGHTS).unwrap();
+        let mut rng = rng();
+
+        for &n in &[0, 4, 1000, 10_000_000] {
+            let counts = table.sample_counts(&mut rng, n);
+            assert_eq!(counts.iter().sum::<u64>(), n);
+            assert_eq!(counts[3], 0);
+        }
+    }
+
+    #[test]
+    fn gamma_functions_match_known_values() {
+        let close = |a: f64, b: f64| (a - b).abs() <= 1e-12 * b.abs().max(1.0);
+
+        assert!(close(ln_gamma(5.0), 24f64.ln()));
+        assert!(close(ln_gamma(0.5), ::std::f64::consts::PI.sqrt().ln()));
+        assert!(close(ln_gamma(0.25), 3.625_609_908_221_908_f64.ln()));
+
+        for &x in &[0.1, 1.0, 2.5, 10.0, 40.0] {
+            // Q(1, x) = e^-x and Q(2, x) = (1 + x) e^-x.
+            assert!(close(upper_gamma_q(1.0, x), (-x).exp()), "Q(1, {})", x);
+            assert!(close(upper_gamma_q(2.0, x), (1.0 + x) * (-x).exp()), "Q(2, {})", x);
+        }
+
+        assert_eq!(upper_gamma_q(3.0, 0.0), 1.0);
+        assert_eq!(upper_gamma_q(3.0, f64::INFINITY), 0.0);
+        assert!(upper_gamma_q(3.0, f64::NAN).is_nan());
+    }
+
+    #[test]
+    fn chi_square_test_accepts_the_table() {
+        let table = AliasTable::new(values(), &WEIGHTS).unwrap();
+        let test = table.chi_square_test(&mut rng(), 100_000);
+
+        assert_eq!(test.degrees_of_freedom, 4);
+        assert!(test.p_value > 0.001, "{:?}", test);
+    }
+
+    #[test]
+    fn dynamic_tables_follow_updates() {
+        let mut table = DynamicAliasTable::new(values(), &WEIGHTS).unwrap();
+        let check = |table: &DynamicAliasTable<char, f64>, weights: &[f64]| {
+            let ps: Vec<f64> = (0..table.len()).map(|i| table.probability_of(i).unwrap()).collect();
+            assert_close(&ps, &normalize(weights));
+        };
+
+        check(&table, &WEIGHTS);
+
+        table.set_weight(3, 1e-6).unwrap();
+        table.set_weight(4, 0.0).unwrap();
+        table.push('g', 1e6).unwrap();
+        check(&table, &[1.0, 2.0, 0.5, 1e-6, 0.0, 3.0, 1e6]);
+
+        assert_eq!(table.remove(6), Ok('g'));
+        assert_eq!(table.remove(1), Ok('b'));
+        assert_eq!(table.values(), &['a', 'c', 'd', 'e', 'f']);
+        check(&table, &[1.0, 0.5, 1e-6, 0.0, 3.0]);
+
+        assert_eq!(table.set_weight(5, 1.0).unwrap_err(),
+                   AliasTableError::InvalidParameter { name: "index" });
+
+        let mut rng = rng();
+        let mut counts = [0usize; 5];
+
+        for (i, _) in (0..100_000).map(|_| table.pick_entry(&mut rng)) {
+            counts[i] += 1;
+        }
+
+        assert_eq!(counts[3], 0);
+        assert!(counts[0] > 21_000 && counts[0] < 23_500, "{:?}", counts);
+        assert!(counts[4] > 65_500 && counts[4] < 68_000, "{:?}", counts);
+
+        // Subnormal weights, whose levels are below `f64::MIN_POSITIVE`.
+        let mut tiny = DynamicAliasTable::new(vec!['a', 'b'], &[1e-310, 1.5e-310]).unwrap();
+        tiny.push('c', 5e-324).unwrap();
+        check(&tiny, &[1e-310, 1.5e-310, 5e-324]);
+
+        let mut counts = [0usize; 3];
+
+        for (i, _) in (0..100_000).map(|_| tiny.pick_entry(&mut rng)) {
+            counts[i] += 1;
+        }
+
+        assert!(counts[0] > 39_000 && counts[0] < 41_000, "{:?}", counts);
+        assert!(counts[2] < 10, "{:?}", counts);
+    }
+
+    #[test]
+    fn dynamic_tables_keep_a_nonzero_weight() {
+        let mut table = DynamicAliasTable::new(vec!['a', 'b'], &[1.0, 0.0]).unwrap();
+
+        assert_eq!(table.set_weight(0, 0.0).unwrap_err(), AliasTableError::ZeroTotalWeight);
+        assert_eq!(table.remove(0).unwrap_err(), AliasTableError::ZeroTotalWeight);
+        assert_eq!(table.remove(1), Ok('b'));
+        assert_eq!(table.sample(&mut rng()), 'a');
+    }
+
+    #[cfg(feature = "num-rational")]
+    #[test]
+    fn integer_weights_are_exact() {
+        use num_rational::Ratio;
+
+        // Neither weight is representable as an `f64`.
+        let weights = [(1u64 << 60) + 1, 3, (1u64 << 53) + 1, 0];
+        let total: u128 = weights.iter().map(|&w| w as u128).sum();
+        let table = AliasTable::from_integer_weights(vec!['a', 'b', 'c', 'd'], &weights).unwrap();
+        let expected: Vec<_> = weights.iter().map(|&w| Ratio::new(w as u128, total)).collect();
+
+        assert_eq!(table.exact_probabilities(), expected);
+    }
+
+    #[cfg(feature = "num-rational")]
+    #[test]
+    fn ratio_weights_are_exact() {
+        use num_rational::Ratio;
+
+        let weights = [Ratio::new(1, 3), Ratio::new(1, 7), Ratio::new(5, 21), Ratio::new(0, 1)];
+        let table = AliasTable::from_ratio_weights(vec!['a', 'b', 'c', 'd'], &weights).unwrap();
+
+        // The weights sum to 5/7.
+        assert_eq!(table.exact_probabilities(),
+                   vec![Ratio::new(7, 15), Ratio::new(1, 5), Ratio::new(1, 3), Ratio::new(0, 1)]);
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn par_new_recovers_weights() {
+        let weights: Vec<f64> = (0..10_000).map(|i| ((i * 7919) % 113) as f64).collect();
+        let table = AliasTable::par_new((0..10_000).collect(), &weights).unwrap();
+        assert_recovers(&table, &weights);
+    }
+
+    #[cfg(feature = "ndarray")]
+    #[test]
+    fn weight_views_recover_weights() {
+        use ndarray::{s, ArrayView1};
+
+        let view = ArrayView1::from(&WEIGHTS[..]);
+        assert_recovers(&AliasTable::from_weight_view(values(), view).unwrap(), &WEIGHTS);
+
+        let strided = view.slice(s![..;2]);
+        assert_recovers(&AliasTable::from_weight_view(vec!['a', 'c', 'e'], strided).unwrap(),
+                        &[1.0, 0.5, 7.25]);
+    }
+
+    #[cfg(feature = "csv")]
+    #[test]
+    fn csv_recovers_weights() {
+        let data = "name, weight\na, 1.0\nb, 2.0\nc, 0.5\n";
+        let table = AliasTable::<String, f64>::from_csv_reader(data.as_bytes(), 0, 1).unwrap();
+        assert_eq!(table.values(), &["a", "b", "c"]);
+        assert_recovers(&table, &[1.0, 2.0, 0.5]);
+
+        let bad = "name, weight\na, 1.0\nb, heavy\n";
+        assert_eq!(AliasTable::<String, f64>::from_csv_reader(bad.as_bytes(), 0, 1).unwrap_err(),
+                   AliasTableError::CsvField { line: 3, column: 1 });
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn config_recovers_weights() {
+        use {WeightedEntry, WeightedList};
+
+        let list = WeightedList {
+            entries: pairs()
+                .into_iter()
+                .map(|(value, weight)| WeightedEntry { value, weight })
+                .collect(),
+        };
+        assert_recovers(&AliasTable::from_config(list).unwrap(), &WEIGHTS);
+    }
+}
@@ -0,0 +1,95 @@
+use num_traits::Float;
+use rand::Rng;
+
+use {AliasTable, AliasTableError, AliasThreshold};
+
+
+/// An alias table with a pity timer on one rare element. Draws follow the table, except that
+/// once `pity` draws in a row have missed the rare element, the next draw is guaranteed to be
+/// the rare element. Drawing the rare element, by luck or by pity, resets the timer.
+#[derive(Clone)]
+pub struct PityTable<T, F> {
+    table: AliasTable<T, F>,
+    rare: usize,
+    pity: usize,
+    misses: usize,
+}
+
+
+impl<T, F> PityTable<T, F>
+    where F: AliasThreshold + Float
+{
+    /// Add a pity timer to a table, guaranteeing that the element at index `rare` is drawn at
+    /// least once in every `pity + 1` draws.
+    pub fn new(table: AliasTable<T, F>, rare: usize, pity: usize) -> Result<Self, AliasTableError> {
+        if rare >= table.objs.len() {
+            return Err(AliasTableError::InvalidParameter { name: "rare" });
+        }
+
+        Ok(PityTable {
+            table,
+            rare,
+            pity,
+            misses: 0,
+        })
+    }
+
+    /// Draw an element, forcing the rare element if the pity timer has run out.
+    pub fn draw<R: Rng>(&mut self, rng: &mut R) -> &T {
+        let idx = if self.misses >= self.pity {
+            self.rare
+        } else {
+            self.table.pick_index(rng)
+        };
+
+        if idx == self.rare {
+            self.misses = 0;
+        } else {
+            self.misses += 1;
+        }
+
+        &self.table.objs[idx]
+    }
+
+    /// The long-run rate at which the rare element is drawn, taking the pity timer into account.
+    /// If the table picks it with probability `p`, the number of draws up to and including each
+    /// rare one averages `(1 - (1 - p)^(pity + 1)) / p`, and the rate is the reciprocal of that.
+    pub fn effective_rate(&self) -> F {
+        let p = self.table.indices.cached_probabilities()[self.rare];
+        let exponent = F::from(self.pity).map_or(F::infinity(), |pity| pity + F::one());
+
+        if p == F::zero() {
+            F::one() / exponent
+        } else {
+            p / (F::one() - (F::one() - p).powf(exponent))
+        }
+    }
+}
+
+
+impl<T, F> PityTable<T, F> {
+    /// The number of draws in a row which have missed the rare element.
+    pub fn misses(&self) -> usize {
+        self.misses
+    }
+
+    /// The number of draws left before the rare element is guaranteed.
+    pub fn draws_until_pity(&self) -> usize {
+        self.pity.saturating_sub(self.misses)
+    }
+
+    /// Reset the pity timer, as if the rare element had just been drawn.
+    pub fn reset(&mut self) {
+        self.misses = 0;
+    }
+
+    /// The table the draws follow.
+    pub fn table(&self) -> &AliasTable<T, F> {
+        &self.table
+    }
+
+    /// Take back the table the draws follow.
+    pub fn into_table(self) -> AliasTable<T, F> {
+        self.table
+    }
+}
@@ -0,0 +1,73 @@
+use std::marker::PhantomData;
+
+use num_traits::Float;
+
+
+/// The two-dimensional Halton sequence, a low-discrepancy sequence of points in `[0, 1)^2` which
+/// can be fed to `AliasIndexTable::pick_points` for quasi-Monte Carlo sampling. The first
+/// coordinate is the base-2 radical inverse of the point's position in the sequence, and the
+/// second is the base-3 radical inverse. The sequence starts at the origin.
+#[derive(Clone, Debug)]
+pub struct HaltonSequence<F> {
+    index: u64,
+    _float: PhantomData<F>,
+}
+
+
+impl<F> HaltonSequence<F> {
+    /// Start a new sequence at the origin.
+    pub fn new() -> Self {
+        HaltonSequence::starting_at(0)
+    }
+
+    /// Start a new sequence at the given position, for instance to continue a sequence or to
+    /// split one between several workers.
+    pub fn starting_at(index: u64) -> Self {
+        HaltonSequence {
+            index,
+            _float: PhantomData,
+        }
+    }
+}
+
+
+impl<F> Default for HaltonSequence<F> {
+    fn default() -> Self {
+        HaltonSequence::new()
+    }
+}
+
+
+impl<F: Float> Iterator for HaltonSequence<F> {
+    type Item = (F, F);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let index = self.index;
+        self.index = self.index.wrapping_add(1);
+
+        let u = index.reverse_bits() as f64 * 2.0f64.powi(-64);
+        let v = radical_inverse(index, 3);
+
+        Some((F::from(u).unwrap_or_else(F::zero), F::from(v).unwrap_or_else(F::zero)))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (usize::MAX, None)
+    }
+}
+
+
+/// Reflect the base-`base` digits of `index` about the radix point.
+fn radical_inverse(mut index: u64, base: u64) -> f64 {
+    let inv_base = 1.0 / base as f64;
+    let mut scale = inv_base;
+    let mut result = 0.0;
+
+    while index > 0 {
+        result += (index % base) as f64 * scale;
+        index /= base;
+        scale *= inv_base;
+    }
+
+    result
+}
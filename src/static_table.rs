@@ -0,0 +1,116 @@
+use rand::Rng;
+
+
+/// An alias table which is built entirely at compile time from integer weights, so that it can be
+/// stored in a `static` and picked from without any startup cost or heap allocation:
+///
+/// `static LOOT: StaticAliasTable<&str, 3> = StaticAliasTable::new(["sword", "shield", "gem"],
+/// [10, 25, 1]);`
+///
+/// Thresholds are stored as exact fractions of the total weight, so the table picks each entry
+/// with exactly its share of the total weight.
+#[derive(Debug)]
+pub struct StaticAliasTable<T, const N: usize> {
+    values: [T; N],
+    thresholds: [u64; N],
+    aliases: [usize; N],
+    total: u64,
+}
+
+
+impl<T, const N: usize> StaticAliasTable<T, N> {
+    /// Build a table picking `values[i]` with probability `weights[i] / sum(weights)`. Panics if
+    /// the table is empty, if every weight is zero, or if the weights sum to more than a `u64` can
+    /// represent; in a `const` or `static` initializer, this is a compile error.
+    pub const fn new(values: [T; N], weights: [u64; N]) -> Self {
+        assert!(N > 0, "a StaticAliasTable must have at least one entry");
+
+        let mut total: u64 = 0;
+        let mut i = 0;
+        while i < N {
+            total = match total.checked_add(weights[i]) {
+                Some(total) => total,
+                None => panic!("the weights of a StaticAliasTable must sum to at most u64::MAX"),
+            };
+            i += 1;
+        }
+
+        assert!(total > 0, "the weights of a StaticAliasTable must not all be zero");
+
+        // Each entry's weight is scaled by `N`, so that a bucket is full when its scaled weight
+        // reaches the total weight. This is exact, so no entries are left over with rounding error.
+        let cap = total as u128;
+        let mut scaled = [0u128; N];
+        let mut small = [0usize; N];
+        let mut large = [0usize; N];
+        let mut n_small = 0;
+        let mut n_large = 0;
+
+        let mut thresholds = [total; N];
+        let mut aliases = [0usize; N];
+
+        i = 0;
+        while i < N {
+            scaled[i] = weights[i] as u128 * N as u128;
+            aliases[i] = i;
+
+            if scaled[i] < cap {
+                small[n_small] = i;
+                n_small += 1;
+            } else {
+                large[n_large] = i;
+                n_large += 1;
+            }
+
+            i += 1;
+        }
+
+        while n_small > 0 && n_large > 0 {
+            n_small -= 1;
+            let s = small[n_small];
+            let l = large[n_large - 1];
+
+            thresholds[s] = scaled[s] as u64;
+            aliases[s] = l;
+
+            scaled[l] -= cap - scaled[s];
+
+            if scaled[l] < cap {
+                n_large -= 1;
+                small[n_small] = l;
+                n_small += 1;
+            }
+        }
+
+        StaticAliasTable {
+            values,
+            thresholds,
+            aliases,
+            total,
+        }
+    }
+
+
+    /// Pick a random index from the distribution.
+    pub fn pick_index<R: Rng>(&self, rng: &mut R) -> usize {
+        let bucket = rng.gen_range(0, N);
+
+        if rng.gen_range(0, self.total) < self.thresholds[bucket] {
+            bucket
+        } else {
+            self.aliases[bucket]
+        }
+    }
+
+
+    /// Pick a random element from the distribution.
+    pub fn pick<R: Rng>(&self, rng: &mut R) -> &T {
+        &self.values[self.pick_index(rng)]
+    }
+
+
+    /// The values of the table, in the order they were given.
+    pub const fn values(&self) -> &[T; N] {
+        &self.values
+    }
+}